@@ -0,0 +1,4 @@
+fn main() {
+    // migrations are embedded into the binary
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,4 @@
+DROP INDEX task_owner_updated_at_idx;
+DROP INDEX task_owner_created_at_idx;
+
+ALTER TABLE task DROP COLUMN completed_at;
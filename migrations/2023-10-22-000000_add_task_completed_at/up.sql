@@ -0,0 +1,6 @@
+ALTER TABLE task ADD COLUMN completed_at TIMESTAMPTZ;
+
+UPDATE task SET completed_at = updated_at WHERE completed;
+
+CREATE INDEX task_owner_created_at_idx ON task (owner, created_at);
+CREATE INDEX task_owner_updated_at_idx ON task (owner, updated_at);
@@ -0,0 +1,7 @@
+DROP INDEX task_owner_due_at_idx;
+
+ALTER TABLE task
+    DROP COLUMN priority,
+    DROP COLUMN due_at;
+
+DROP TYPE task_priority;
@@ -0,0 +1,7 @@
+CREATE TYPE task_priority AS ENUM ('low', 'medium', 'high', 'urgent');
+
+ALTER TABLE task
+    ADD COLUMN due_at TIMESTAMPTZ,
+    ADD COLUMN priority task_priority NOT NULL DEFAULT 'medium';
+
+CREATE INDEX task_owner_due_at_idx ON task (owner, due_at) WHERE due_at IS NOT NULL;
@@ -0,0 +1,15 @@
+CREATE TABLE tag (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (owner, name)
+);
+
+CREATE TABLE task_tag (
+    task_id UUID NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, tag_id)
+);
+
+CREATE INDEX task_tag_tag_id_idx ON task_tag (tag_id);
@@ -0,0 +1,2 @@
+ALTER TABLE task DROP COLUMN project_id;
+DROP TABLE project;
@@ -0,0 +1,15 @@
+CREATE TABLE project (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX project_owner_idx ON project (owner);
+
+-- tasks without a project are in the owner's inbox
+ALTER TABLE task ADD COLUMN project_id UUID REFERENCES project (id) ON DELETE SET NULL;
+
+CREATE INDEX task_owner_project_id_idx ON task (owner, project_id);
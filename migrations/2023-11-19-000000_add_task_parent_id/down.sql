@@ -0,0 +1,2 @@
+ALTER TABLE task DROP COLUMN auto_complete;
+ALTER TABLE task DROP COLUMN parent_id;
@@ -0,0 +1,5 @@
+-- deleting a parent removes its subtree unless the service promotes the children first
+ALTER TABLE task ADD COLUMN parent_id UUID REFERENCES task (id) ON DELETE CASCADE;
+ALTER TABLE task ADD COLUMN auto_complete BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX task_parent_id_idx ON task (parent_id);
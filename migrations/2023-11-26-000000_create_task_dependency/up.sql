@@ -0,0 +1,10 @@
+-- task_id cannot start until blocker_id is completed
+CREATE TABLE task_dependency (
+    task_id UUID NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    blocker_id UUID NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (task_id, blocker_id),
+    CHECK (task_id <> blocker_id)
+);
+
+CREATE INDEX task_dependency_blocker_id_idx ON task_dependency (blocker_id);
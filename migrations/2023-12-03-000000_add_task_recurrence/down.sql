@@ -0,0 +1,2 @@
+ALTER TABLE task DROP COLUMN recurrence_start;
+ALTER TABLE task DROP COLUMN recurrence;
@@ -0,0 +1,3 @@
+-- recurrence holds an RRULE, its occurrences are counted from recurrence_start
+ALTER TABLE task ADD COLUMN recurrence TEXT;
+ALTER TABLE task ADD COLUMN recurrence_start TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- maintained by postgres from title and description, titles rank above descriptions.
+-- the column is only read through raw sql, so it is left out of the diesel schema
+ALTER TABLE task ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') ||
+    setweight(to_tsvector('english', description), 'B')
+) STORED;
+
+CREATE INDEX task_search_vector_idx ON task USING GIN (search_vector);
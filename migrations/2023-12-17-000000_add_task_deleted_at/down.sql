@@ -0,0 +1,2 @@
+DROP INDEX task_deleted_at_idx;
+ALTER TABLE task DROP COLUMN deleted_at;
@@ -0,0 +1,4 @@
+-- deleted tasks stay in the trash until restored or purged
+ALTER TABLE task ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX task_deleted_at_idx ON task (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,4 @@
+DROP TRIGGER task_event_append_only ON task_event;
+DROP FUNCTION task_event_append_only();
+DROP TABLE task_event;
+DROP TYPE task_action;
@@ -0,0 +1,26 @@
+CREATE TYPE task_action AS ENUM ('created', 'updated', 'deleted', 'restored', 'purged');
+
+-- no foreign key to task, the history of a task outlives its purge
+CREATE TABLE task_event (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL,
+    owner TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    action task_action NOT NULL,
+    ref_id TEXT,
+    changes JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX task_event_task_id_idx ON task_event (task_id, created_at);
+CREATE INDEX task_event_owner_idx ON task_event (owner, created_at);
+
+CREATE FUNCTION task_event_append_only() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'task_event is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_event_append_only
+    BEFORE UPDATE OR DELETE ON task_event
+    FOR EACH ROW EXECUTE FUNCTION task_event_append_only();
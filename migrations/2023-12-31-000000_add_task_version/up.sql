@@ -0,0 +1,2 @@
+-- incremented on every write, clients send it back in If-Match
+ALTER TABLE task ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
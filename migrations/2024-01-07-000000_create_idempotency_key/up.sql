@@ -0,0 +1,10 @@
+-- Idempotency-Key of POST /task, a retry within the ttl gets the first response back
+CREATE TABLE idempotency_key (
+    owner TEXT NOT NULL,
+    key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    response JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (owner, key)
+);
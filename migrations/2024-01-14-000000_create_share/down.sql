@@ -0,0 +1,2 @@
+DROP TABLE share;
+DROP TYPE share_role;
@@ -0,0 +1,17 @@
+CREATE TYPE share_role AS ENUM ('viewer', 'editor', 'owner');
+
+-- grants another user a role on a task, or on every task of a project
+CREATE TABLE share (
+    id UUID PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    task_id UUID REFERENCES task (id) ON DELETE CASCADE,
+    project_id UUID REFERENCES project (id) ON DELETE CASCADE,
+    role share_role NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    CHECK ((task_id IS NULL) <> (project_id IS NULL))
+);
+
+CREATE UNIQUE INDEX share_task_id_user_id_idx ON share (task_id, user_id) WHERE task_id IS NOT NULL;
+CREATE UNIQUE INDEX share_project_id_user_id_idx ON share (project_id, user_id) WHERE project_id IS NOT NULL;
+CREATE INDEX share_user_id_idx ON share (user_id);
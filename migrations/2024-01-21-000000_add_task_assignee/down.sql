@@ -0,0 +1,9 @@
+-- enum values cannot be dropped, so the type is rebuilt with assignments kept as updates
+ALTER TYPE task_action RENAME TO task_action_old;
+CREATE TYPE task_action AS ENUM ('created', 'updated', 'deleted', 'restored', 'purged');
+ALTER TABLE task_event ALTER COLUMN action TYPE task_action
+    USING (CASE WHEN action = 'assigned' THEN 'updated' ELSE action::text END)::task_action;
+DROP TYPE task_action_old;
+
+DROP INDEX task_assignee_id_idx;
+ALTER TABLE task DROP COLUMN assignee_id;
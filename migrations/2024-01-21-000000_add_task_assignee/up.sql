@@ -0,0 +1,6 @@
+-- the user the task is assigned to, who must be able to access it
+ALTER TABLE task ADD COLUMN assignee_id TEXT;
+
+CREATE INDEX task_assignee_id_idx ON task (assignee_id) WHERE assignee_id IS NOT NULL;
+
+ALTER TYPE task_action ADD VALUE 'assigned';
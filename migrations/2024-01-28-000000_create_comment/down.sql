@@ -0,0 +1,2 @@
+DROP TABLE comment_mention;
+DROP TABLE comment;
@@ -0,0 +1,23 @@
+-- comments go with their task when it is purged, a trashed task keeps them
+CREATE TABLE comment (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    -- markdown, stored as written
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX comment_task_id_idx ON comment (task_id, created_at, id);
+
+-- the users a comment mentions, notified_at is set once they have been told
+CREATE TABLE comment_mention (
+    comment_id UUID NOT NULL REFERENCES comment (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    notified_at TIMESTAMPTZ,
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX comment_mention_pending_idx ON comment_mention (created_at) WHERE notified_at IS NULL;
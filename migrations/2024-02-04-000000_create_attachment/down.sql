@@ -0,0 +1,4 @@
+DROP TRIGGER attachment_orphaned_blob ON attachment;
+DROP FUNCTION attachment_orphaned_blob();
+DROP TABLE orphaned_blob;
+DROP TABLE attachment;
@@ -0,0 +1,35 @@
+-- the bytes live in blob storage under storage_key, a row goes with its task
+-- when it is purged while a trashed task keeps them
+CREATE TABLE attachment (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    uploader TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    -- hex sha256 of the content, served as the ETag
+    checksum TEXT NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX attachment_task_id_idx ON attachment (task_id, created_at, id);
+
+-- blobs whose row is gone, however it went, until they are deleted from storage
+CREATE TABLE orphaned_blob (
+    storage_key TEXT PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE FUNCTION attachment_orphaned_blob() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO orphaned_blob (storage_key, created_at)
+    VALUES (OLD.storage_key, now())
+    ON CONFLICT DO NOTHING;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER attachment_orphaned_blob
+    AFTER DELETE ON attachment
+    FOR EACH ROW EXECUTE FUNCTION attachment_orphaned_blob();
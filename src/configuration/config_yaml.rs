@@ -8,5 +8,5 @@ pub fn load_config(path: &str) -> Result<AppConfig, Box<dyn Error>> {
     let app_config: AppConfig = serde_yaml::from_reader(f)?;
     //.expect("read value from file error");
 
-    return Ok(app_config);
+    Ok(app_config)
 }
@@ -1,3 +1,4 @@
+use crate::model::task_manager::SubtaskDeleteMode;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
@@ -6,6 +7,11 @@ pub struct AppConfig {
     pub http_server: HttpServer,
     pub database: Database,
     pub redis: Redis,
+    pub auth: Auth,
+    pub task: Task,
+    pub trash: Trash,
+    pub idempotency: Idempotency,
+    pub attachment: Attachment,
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -14,19 +20,132 @@ pub struct HttpServer {
     pub port: u16,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize, Default)]
 pub struct Database {
     pub username: String,
     pub password: String,
     pub host: String,
     pub port: String,
     pub database: String,
+    pub pool_size: u32,
+    pub acquire_timeout: u64,
+    pub idle_timeout: u64,
+    pub run_migrations: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+// the config is logged at start up, the password must not be
+impl Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("database", &self.database)
+            .field("pool_size", &self.pool_size)
+            .field("acquire_timeout", &self.acquire_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("run_migrations", &self.run_migrations)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct Redis {
     pub host: String,
     pub port: String,
     pub database: String,
     pub timeout: u64,
+    pub acquire_timeout: u64,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct Auth {
+    pub algorithm: String,
+    pub secret: Option<String>,
+    pub public_key_path: Option<String>,
+    pub jwks_path: Option<String>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub leeway: u64,
+}
+
+// the config is logged at start up, the signing secret must not be
+impl Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auth")
+            .field("algorithm", &self.algorithm)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("public_key_path", &self.public_key_path)
+            .field("jwks_path", &self.jwks_path)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("leeway", &self.leeway)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Task {
+    pub max_depth: usize,
+    pub subtask_delete: SubtaskDeleteMode,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Trash {
+    pub retention_days: u32,
+    pub purge_interval: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Idempotency {
+    pub ttl: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Attachment {
+    pub max_size: u64,
+    pub allowed_types: Vec<String>,
+    pub cleanup_interval: u64,
+    pub storage: StorageBackend,
+    pub local: Option<LocalStorage>,
+    pub s3: Option<S3Storage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct LocalStorage {
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct S3Storage {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+}
+
+// the config is logged at start up, the secret key must not be
+impl Debug for S3Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Storage")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("access_key", &self.access_key)
+            .field(
+                "secret_key",
+                &self.secret_key.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
 }
@@ -1,12 +1,17 @@
 use crate::configuration::model::Redis;
-use redis::{Commands, Connection, RedisError};
+use crate::model::error::AppError;
+use actix_web::rt::time::timeout;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::{error::Error, time::Duration};
+use std::future::Future;
+use std::time::Duration;
 
+#[derive(Clone)]
 pub struct Client {
     config: Redis,
-    connection: Option<Connection>,
+    connection: Option<ConnectionManager>,
 }
 
 impl Client {
@@ -17,74 +22,89 @@ impl Client {
         }
     }
 
-    pub fn connect_redis(&mut self) -> Result<(), RedisError> {
+    pub async fn connect_redis(&mut self) -> Result<(), AppError> {
         let redis_url = format!(
             "redis://{}:{}/{}",
             self.config.host, self.config.port, self.config.database
         );
 
-        let redis_client = match redis::Client::open(redis_url) {
-            Ok(client) => client,
-            Err(err) => return Err(err),
-        };
+        let redis_client = redis::Client::open(redis_url)?;
 
-        let timeout = Duration::new(self.config.timeout, 0);
-        let connection = match redis_client.get_connection_with_timeout(timeout) {
-            Ok(connection) => connection,
-            Err(err) => return Err(err),
-        };
+        // the manager multiplexes every request over one connection and
+        // reconnects on its own, so it is cloned instead of pooled
+        let acquire_timeout = Duration::from_secs(self.config.acquire_timeout);
+        let connection = timeout(acquire_timeout, ConnectionManager::new(redis_client))
+            .await
+            .map_err(|_| AppError::Cache("connect redis timed out".to_string()))??;
 
         self.connection = Some(connection);
         Ok(())
     }
 
-    pub fn get<T>(&mut self, key: String) -> Result<T, Box<dyn Error>>
+    pub async fn get<T>(&self, key: String) -> Result<T, AppError>
     where
         T: DeserializeOwned,
     {
-        let connection = self
-            .connection
-            .as_mut()
-            .expect("redis connection has not been initiated");
+        let mut connection = self.connection()?;
 
-        let data: String = match connection.get(key) {
-            Ok(result) => result,
-            Err(err) => return Err(Box::new(err)),
-        };
+        let data: String = self.with_timeout(connection.get(key)).await?;
 
-        let result: T = serde_json::from_str(&data)?;
+        let result: T =
+            serde_json::from_str(&data).map_err(|err| AppError::Cache(err.to_string()))?;
         Ok(result)
     }
 
-    pub fn set<T>(&mut self, key: String, value: &T) -> Result<(), Box<dyn Error>>
+    pub async fn set<T>(&self, key: String, value: &T) -> Result<(), AppError>
     where
         T: Serialize,
     {
-        let connection = self
-            .connection
-            .as_mut()
-            .expect("redis connection has not been initiated");
-
-        let value_json = match serde_json::to_string(value) {
-            Ok(result) => result,
-            Err(err) => return Err(Box::new(err)),
-        };
-
-        let ttl = std::time::Duration::from_secs(5 * 60) // 5 minutes
-            .as_secs()
-            .try_into()
-            .unwrap();
-        connection.set_ex(key, value_json, ttl)?;
-        Ok(())
+        self.set_with_ttl(key, value, 5 * 60).await // 5 minutes
     }
 
-    pub fn delete(&mut self, key: String) -> Result<(), Box<dyn Error>> {
-        let connection = self
-            .connection
-            .as_mut()
-            .expect("redis connection has not been initiated");
+    pub async fn set_with_ttl<T>(&self, key: String, value: &T, ttl: u64) -> Result<(), AppError>
+    where
+        T: Serialize,
+    {
+        let mut connection = self.connection()?;
+
+        let value_json =
+            serde_json::to_string(value).map_err(|err| AppError::Cache(err.to_string()))?;
 
-        connection.del(key)?;
+        let ttl = ttl.try_into().unwrap_or(usize::MAX);
+        self.with_timeout(connection.set_ex::<_, _, ()>(key, value_json, ttl))
+            .await?;
         Ok(())
     }
+
+    pub async fn get_counter(&self, key: String) -> Result<i64, AppError> {
+        let mut connection = self.connection()?;
+
+        let counter: Option<i64> = self.with_timeout(connection.get(key)).await?;
+        Ok(counter.unwrap_or_default())
+    }
+
+    pub async fn increment(&self, key: String) -> Result<i64, AppError> {
+        let mut connection = self.connection()?;
+
+        let counter: i64 = self.with_timeout(connection.incr(key, 1)).await?;
+        Ok(counter)
+    }
+
+    fn connection(&self) -> Result<ConnectionManager, AppError> {
+        self.connection
+            .clone()
+            .ok_or_else(|| AppError::Cache("redis connection has not been initiated".into()))
+    }
+
+    async fn with_timeout<F, T>(&self, command: F) -> Result<T, AppError>
+    where
+        F: Future<Output = redis::RedisResult<T>>,
+    {
+        let command_timeout = Duration::from_secs(self.config.timeout);
+        let result = timeout(command_timeout, command)
+            .await
+            .map_err(|_| AppError::Cache("redis command timed out".to_string()))??;
+
+        Ok(result)
+    }
 }
@@ -0,0 +1,17 @@
+use crate::database::postgres::PgPool;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::error::Error;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+pub fn run_pending_migrations(pool: &PgPool) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let mut connection = pool.get()?;
+
+    let versions = connection
+        .run_pending_migrations(MIGRATIONS)?
+        .iter()
+        .map(|version| version.to_string())
+        .collect();
+
+    Ok(versions)
+}
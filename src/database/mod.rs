@@ -1,2 +1,5 @@
-pub mod postgres;
 pub mod cache;
+pub mod migration;
+pub mod postgres;
+#[cfg(test)]
+pub mod testing;
@@ -1,17 +1,39 @@
 use crate::configuration::model::Database;
+use crate::model::error::AppError;
+use actix_web::web;
 use diesel::pg::PgConnection;
-use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool, PoolError};
+use std::time::Duration;
 
-pub fn connect_database(config: Database) -> Result<PgConnection, ConnectionError> {
+pub type PgPool = Pool<ConnectionManager<PgConnection>>;
+
+pub fn connect_database(config: Database) -> Result<PgPool, PoolError> {
     let database_url = format!(
-        "postgres://{}:{}@{}/{}",
-        config.username, config.password, config.host, config.database,
+        "postgres://{}:{}@{}:{}/{}",
+        config.username, config.password, config.host, config.port, config.database,
     );
 
-    let pg_connection = match PgConnection::establish(&database_url) {
-        Ok(connection) => connection,
-        Err(err) => return Err(err),
-    };
+    let manager = ConnectionManager::<PgConnection>::new(database_url);
+    let pg_pool = Pool::builder()
+        .max_size(config.pool_size)
+        .connection_timeout(Duration::from_secs(config.acquire_timeout))
+        .idle_timeout(Some(Duration::from_secs(config.idle_timeout)))
+        .build(manager)?;
+
+    Ok(pg_pool)
+}
 
-    Ok(pg_connection)
+// diesel is blocking, so queries run on the actix blocking thread pool
+// with a pooled connection instead of stalling the worker executor
+pub async fn run<F, R>(pool: &PgPool, query: F) -> Result<R, AppError>
+where
+    F: FnOnce(&mut PgConnection) -> Result<R, AppError> + Send + 'static,
+    R: Send + 'static,
+{
+    let pool = pool.clone();
+    web::block(move || {
+        let mut connection = pool.get()?;
+        query(&mut connection)
+    })
+    .await?
 }
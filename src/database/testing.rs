@@ -0,0 +1,36 @@
+use crate::configuration::model::{Database, Redis};
+use crate::database::cache::Client;
+use crate::database::migration;
+use crate::database::postgres::{self, PgPool};
+use std::env;
+
+// the postgres and redis of docker-compose, TEST_POSTGRES_HOST and
+// TEST_REDIS_HOST point the tests elsewhere
+pub fn database() -> PgPool {
+    let db_pool = postgres::connect_database(Database {
+        username: "postgresusr".to_string(),
+        password: "1234".to_string(),
+        host: env::var("TEST_POSTGRES_HOST").unwrap_or_else(|_| "localhost".to_string()),
+        port: "5432".to_string(),
+        database: "songvutdb".to_string(),
+        pool_size: 3,
+        acquire_timeout: 5,
+        idle_timeout: 60,
+        run_migrations: true,
+    })
+    .expect("connect postgres");
+    migration::run_pending_migrations(&db_pool).expect("run migrations");
+    db_pool
+}
+
+pub async fn cache() -> Client {
+    let mut redis_client = Client::new(Redis {
+        host: env::var("TEST_REDIS_HOST").unwrap_or_else(|_| "localhost".to_string()),
+        port: "6379".to_string(),
+        database: "0".to_string(),
+        timeout: 5,
+        acquire_timeout: 5,
+    });
+    redis_client.connect_redis().await.expect("connect redis");
+    redis_client
+}
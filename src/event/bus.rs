@@ -0,0 +1,36 @@
+use crate::model::task_event::TaskAssigned;
+
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+// events are kept until every subscriber has read them, one that falls
+// further behind than this misses the oldest ones
+const CAPACITY: usize = 1024;
+
+// fans committed changes out to the subscribers within the process, the
+// history in task_event stays the record of what happened
+#[derive(Clone)]
+pub struct EventBus {
+    assignments: Sender<TaskAssigned>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (assignments, _) = broadcast::channel(CAPACITY);
+        EventBus { assignments }
+    }
+
+    pub fn subscribe_assignments(&self) -> Receiver<TaskAssigned> {
+        self.assignments.subscribe()
+    }
+
+    pub fn publish_assignment(&self, event: TaskAssigned) {
+        // sending only fails while nobody is subscribed, then there is nobody to tell
+        let _ = self.assignments.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,230 @@
+use crate::handler::task_manager::{error_response, get_ref_id, get_user_id};
+use crate::model::attachment::Upload;
+use crate::model::error::AppError;
+use crate::model::response;
+use crate::service::attachment::AttachmentService;
+use crate::service::interface::AttachmentServiceInterface;
+
+use actix_multipart::{Multipart, MultipartError};
+use actix_web::{
+    http::header::{
+        self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+        HeaderMap,
+    },
+    web::{Data, Path},
+    HttpRequest, HttpResponse, Responder,
+};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use log::error;
+
+// the part of the multipart body that carries the file
+const FILE_FIELD: &str = "file";
+
+pub async fn get_attachment(
+    task_id: Path<String>,
+    req: HttpRequest,
+    service: Data<AttachmentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.find_all(task_id.into_inner(), user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Attachments(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get attachment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn create_attachment(
+    task_id: Path<String>,
+    req: HttpRequest,
+    payload: Multipart,
+    service: Data<AttachmentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let upload = match read_upload(payload).await {
+        Ok(upload) => upload,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.insert(upload, task_id.into_inner(), user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Attachment(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("create attachment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn download_attachment(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<AttachmentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (task_id, attachment_id) = path.into_inner();
+    let range = get_header(req.headers(), header::RANGE);
+    let if_range = get_header(req.headers(), header::IF_RANGE);
+    match service
+        .download(task_id, attachment_id, user_id, range, if_range)
+        .await
+    {
+        Ok(download) => {
+            let attachment = download.attachment;
+            let size = attachment.size as u64;
+
+            let mut builder = match download.range {
+                Some((start, end)) => {
+                    let mut builder = HttpResponse::PartialContent();
+                    builder.insert_header((
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end, size),
+                    ));
+                    builder
+                }
+                None => HttpResponse::Ok(),
+            };
+            let length = download.range.map_or(size, |(start, end)| end - start + 1);
+
+            // always a download, never rendered in place as whatever it claims to be
+            builder
+                .insert_header(("x-ref-id", x_ref_id))
+                .insert_header((header::CONTENT_TYPE, attachment.content_type.as_str()))
+                .insert_header(content_disposition(&attachment.file_name))
+                .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+                .insert_header((header::ACCEPT_RANGES, "bytes"))
+                .insert_header((header::ETAG, attachment.etag()))
+                .no_chunking(length)
+                .streaming(download.body)
+        }
+        Err(err) => {
+            error!("download attachment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn delete_attachment(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<AttachmentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (task_id, attachment_id) = path.into_inner();
+    match service.delete(task_id, attachment_id, user_id).await {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete attachment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+// the first part named file, the parts before it are skipped
+async fn read_upload(mut payload: Multipart) -> Result<Upload, AppError> {
+    while let Some(field) = payload.try_next().await.map_err(multipart_error)? {
+        if field.name() != FILE_FIELD {
+            continue;
+        }
+
+        let file_name = field
+            .content_disposition()
+            .get_filename()
+            .map(str::to_string);
+        let content_type = field.content_type().map(|mime| mime.to_string());
+
+        // the multipart has to outlive the field, a field of a dropped
+        // multipart never ends
+        let body = stream::unfold((field, payload), |(mut field, payload)| async move {
+            let chunk = field.next().await?;
+            Some((chunk.map_err(multipart_error), (field, payload)))
+        })
+        .boxed_local();
+
+        return Ok(Upload {
+            file_name,
+            content_type,
+            body,
+        });
+    }
+
+    Err(AppError::Validation(format!(
+        "multipart body has no {} part",
+        FILE_FIELD
+    )))
+}
+
+fn multipart_error(err: MultipartError) -> AppError {
+    AppError::Validation(format!("invalid multipart body: {}", err))
+}
+
+fn get_header(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+// the name as it was uploaded, with a plain ascii fallback for older clients
+fn content_disposition(file_name: &str) -> ContentDisposition {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '_' })
+        .collect();
+    let mut parameters = vec![DispositionParam::Filename(fallback)];
+    if !file_name.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: file_name.as_bytes().to_vec(),
+        }));
+    }
+
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters,
+    }
+}
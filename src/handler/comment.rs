@@ -0,0 +1,132 @@
+use crate::handler::task_manager::{error_response, get_ref_id, get_user_id};
+use crate::model::{request, response};
+use crate::service::comment::CommentService;
+use crate::service::interface::CommentServiceInterface;
+
+use actix_web::{
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+pub async fn get_comment(
+    task_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::PageQuery>,
+    service: Data<CommentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .find_all(task_id.into_inner(), user_id, query.into_inner())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_comment_page_response("200", "success", result);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get comment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn create_comment(
+    task_id: Path<String>,
+    req: HttpRequest,
+    comment_request: Json<request::CommentRequest>,
+    service: Data<CommentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .insert(comment_request.into_inner(), task_id.into_inner(), user_id)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Comment(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("create comment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn update_comment(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    comment_request: Json<request::CommentRequest>,
+    service: Data<CommentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (task_id, comment_id) = path.into_inner();
+    match service
+        .update(comment_request.into_inner(), task_id, comment_id, user_id)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Comment(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("update comment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn delete_comment(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<CommentService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (task_id, comment_id) = path.into_inner();
+    match service.delete(task_id, comment_id, user_id).await {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete comment error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::handler::task_manager::{error_response, get_ref_id, get_tags, get_user_id};
+use crate::model::{request, response};
+use crate::service::interface::{ProjectServiceInterface, TaskServiceInterface};
+use crate::service::project::ProjectService;
+use crate::service::task_manager::TaskService;
+
+use actix_web::{
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+pub async fn get_project(req: HttpRequest, service: Data<ProjectService>) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.find_all(user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Projects(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get project error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_project_by_id(
+    project_id: Path<String>,
+    req: HttpRequest,
+    service: Data<ProjectService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.find_by_id(project_id.to_string(), user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Project(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get project by id error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_project_task(
+    project_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::TaskQuery>,
+    service: Data<ProjectService>,
+    task_service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    // an unknown project is a 404 rather than an empty list
+    let project = match service
+        .find_by_id(project_id.to_string(), user_id.to_string())
+        .await
+    {
+        Ok(project) => project,
+        Err(err) => {
+            error!("get project task error: {:?}", err);
+            return error_response(err, x_ref_id);
+        }
+    };
+
+    let mut query = query.into_inner();
+    query.tag = get_tags(&req);
+    query.project_id = Some(project.id.to_string());
+
+    match task_service.find_all(user_id, query).await {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get project task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn create_project(
+    req: HttpRequest,
+    project_request: Json<request::ProjectRequest>,
+    service: Data<ProjectService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.insert(project_request.into_inner(), user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Project(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("create project error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn update_project_by_id(
+    project_id: Path<String>,
+    req: HttpRequest,
+    project_request: Json<request::ProjectRequest>,
+    service: Data<ProjectService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .update(
+            project_request.into_inner(),
+            project_id.to_string(),
+            user_id,
+        )
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Project(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("update project error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn delete_project_by_id(
+    project_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::ProjectDeleteQuery>,
+    service: Data<ProjectService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .delete(
+            query.into_inner(),
+            project_id.to_string(),
+            user_id,
+            x_ref_id.clone(),
+        )
+        .await
+    {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete project error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
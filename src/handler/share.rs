@@ -0,0 +1,221 @@
+use crate::handler::task_manager::{error_response, get_ref_id, get_user_id};
+use crate::model::share::ShareKind;
+use crate::model::{request, response};
+use crate::service::interface::ShareServiceInterface;
+use crate::service::share::ShareService;
+
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+pub async fn get_task_share(
+    task_id: Path<String>,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> impl Responder {
+    get_share(ShareKind::Task, task_id.into_inner(), req, service).await
+}
+
+pub async fn get_project_share(
+    project_id: Path<String>,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> impl Responder {
+    get_share(ShareKind::Project, project_id.into_inner(), req, service).await
+}
+
+pub async fn create_task_share(
+    task_id: Path<String>,
+    req: HttpRequest,
+    share_request: Json<request::ShareRequest>,
+    service: Data<ShareService>,
+) -> impl Responder {
+    create_share(
+        ShareKind::Task,
+        task_id.into_inner(),
+        req,
+        share_request,
+        service,
+    )
+    .await
+}
+
+pub async fn create_project_share(
+    project_id: Path<String>,
+    req: HttpRequest,
+    share_request: Json<request::ShareRequest>,
+    service: Data<ShareService>,
+) -> impl Responder {
+    create_share(
+        ShareKind::Project,
+        project_id.into_inner(),
+        req,
+        share_request,
+        service,
+    )
+    .await
+}
+
+pub async fn update_task_share(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    share_request: Json<request::ShareRoleRequest>,
+    service: Data<ShareService>,
+) -> impl Responder {
+    update_share(ShareKind::Task, path, req, share_request, service).await
+}
+
+pub async fn update_project_share(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    share_request: Json<request::ShareRoleRequest>,
+    service: Data<ShareService>,
+) -> impl Responder {
+    update_share(ShareKind::Project, path, req, share_request, service).await
+}
+
+pub async fn delete_task_share(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> impl Responder {
+    delete_share(ShareKind::Task, path, req, service).await
+}
+
+pub async fn delete_project_share(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> impl Responder {
+    delete_share(ShareKind::Project, path, req, service).await
+}
+
+// tasks and projects are shared the same way, the routes only differ in the kind
+
+async fn get_share(
+    kind: ShareKind,
+    target_id: String,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> HttpResponse {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.find_all(kind, target_id, user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Shares(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get {} share error: {:?}", kind.as_str(), err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+async fn create_share(
+    kind: ShareKind,
+    target_id: String,
+    req: HttpRequest,
+    share_request: Json<request::ShareRequest>,
+    service: Data<ShareService>,
+) -> HttpResponse {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .insert(share_request.into_inner(), kind, target_id, user_id)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Share(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("create {} share error: {:?}", kind.as_str(), err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+async fn update_share(
+    kind: ShareKind,
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    share_request: Json<request::ShareRoleRequest>,
+    service: Data<ShareService>,
+) -> HttpResponse {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (target_id, member) = path.into_inner();
+    match service
+        .update(share_request.into_inner(), kind, target_id, member, user_id)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Share(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("update {} share error: {:?}", kind.as_str(), err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+async fn delete_share(
+    kind: ShareKind,
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<ShareService>,
+) -> HttpResponse {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (target_id, member) = path.into_inner();
+    match service.delete(kind, target_id, member, user_id).await {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete {} share error: {:?}", kind.as_str(), err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::handler::task_manager::{error_response, get_ref_id, get_user_id};
+use crate::model::{request, response};
+use crate::service::interface::TagServiceInterface;
+use crate::service::tag::TagService;
+
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+pub async fn get_tag(req: HttpRequest, service: Data<TagService>) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.find_all(user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Tags(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get tag error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn create_tag(
+    req: HttpRequest,
+    tag_request: Json<request::TagRequest>,
+    service: Data<TagService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.insert(tag_request.into_inner(), user_id).await {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Tag(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("create tag error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn update_tag_by_id(
+    tag_id: Path<String>,
+    req: HttpRequest,
+    tag_request: Json<request::TagRequest>,
+    service: Data<TagService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .update(tag_request.into_inner(), tag_id.to_string(), user_id)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Tag(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("update tag error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn delete_tag_by_id(
+    tag_id: Path<String>,
+    req: HttpRequest,
+    service: Data<TagService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service.delete(tag_id.to_string(), user_id).await {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete tag error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
@@ -1,117 +1,336 @@
+use crate::model::auth::Principal;
+use crate::model::error::AppError;
+use crate::model::task_manager::Task;
 use crate::model::{request, response};
 use crate::service::interface::TaskServiceInterface;
 use crate::service::task_manager::TaskService;
-// use crate::util::token;
 
 use actix_web::{
-    http::header::HeaderMap,
-    web::{Data, Json, Path},
-    HttpRequest, HttpResponse, Responder,
+    http::{
+        header::{
+            ETag, EntityTag, Header, HeaderMap, HeaderName, HeaderValue, IfMatch, IfNoneMatch,
+        },
+        StatusCode,
+    },
+    web::{Data, Json, Path, Query},
+    HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError,
 };
 use log::error;
-use std::error::Error;
-use std::sync::Mutex;
-
-pub async fn get_task(req: HttpRequest, data: Data<Mutex<TaskService>>) -> impl Responder {
-    let mut service = data.lock().unwrap();
 
+pub async fn get_task(
+    req: HttpRequest,
+    query: Query<request::TaskQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
     let x_ref_id = get_ref_id(req.headers());
-    let user_id = match get_user_id(req.headers()) {
+    let user_id = match get_user_id(&req) {
         Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let mut query = query.into_inner();
+    query.tag = get_tags(&req);
+
+    match service.find_all(user_id.to_string(), query).await {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
         Err(err) => {
-            error!("get user_id error: {:}", err);
+            error!("get task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_overdue_task(
+    req: HttpRequest,
+    query: Query<request::TaskQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let mut query = query.into_inner();
+    query.tag = get_tags(&req);
 
-            return HttpResponse::Unauthorized()
+    match service.find_overdue(user_id.to_string(), query).await {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
+            HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
-                .finish();
+                .json(response)
+        }
+        Err(err) => {
+            error!("get overdue task error: {:?}", err);
+            error_response(err, x_ref_id)
         }
+    }
+}
+
+pub async fn search_task(
+    req: HttpRequest,
+    query: Query<request::TaskSearchQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
     };
 
-    match service.find_all(user_id.to_string()) {
+    match service
+        .search(user_id.to_string(), query.into_inner())
+        .await
+    {
         Ok(result) => {
-            let response = response::create_task_response(
-                "200",
-                "success",
-                Some(response::TaskResponseData::Tasks(result)),
-            );
+            let response = response::create_task_search_response("200", "success", result);
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
         Err(err) => {
-            error!("get task error: {:?}", err);
+            error!("search task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_due_task(
+    req: HttpRequest,
+    due_query: Query<request::DueQuery>,
+    query: Query<request::TaskQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
 
-            let response = response::create_task_response("500", err.to_string().as_str(), None);
+    let mut query = query.into_inner();
+    query.tag = get_tags(&req);
+
+    match service
+        .find_due(user_id.to_string(), due_query.into_inner(), query)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
+        Err(err) => {
+            error!("get due task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
     }
 }
 
 pub async fn get_task_by_id(
     req: HttpRequest,
     task_id: Path<String>,
-    data: Data<Mutex<TaskService>>,
+    service: Data<TaskService>,
 ) -> impl Responder {
-    let mut service = data.lock().unwrap();
-
     let x_ref_id = get_ref_id(req.headers());
-    let user_id = match get_user_id(req.headers()) {
+    let user_id = match get_user_id(&req) {
         Ok(user_id) => user_id,
-        Err(err) => {
-            error!("get user_id error: {:?}", err);
+        Err(err) => return error_response(err, x_ref_id),
+    };
 
-            return HttpResponse::Unauthorized()
+    match service
+        .find_by_id(task_id.to_string(), user_id.to_string())
+        .await
+    {
+        Ok(result) => {
+            let etag = task_etag(&result.task);
+            if is_not_modified(&req, &etag) {
+                return HttpResponse::NotModified()
+                    .insert_header(("x-ref-id", x_ref_id))
+                    .insert_header(etag)
+                    .finish();
+            }
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Task(result)),
+            );
+            HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
-                .finish();
+                .insert_header(etag)
+                .json(response)
+        }
+        Err(err) => {
+            error!("get task by id error: {:?}", err);
+            error_response(err, x_ref_id)
         }
+    }
+}
+
+pub async fn create_task(
+    req: HttpRequest,
+    task_request: Json<request::TaskRequest>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
     };
 
-    match service.find_by_id(task_id.to_string(), user_id.to_string()) {
+    // service
+    match service
+        .insert(
+            task_request.into_inner(),
+            user_id,
+            get_idempotency_key(req.headers()),
+            x_ref_id.clone(),
+        )
+        .await
+    {
         Ok(result) => {
+            let etag = task_etag(&result.task);
             let response = response::create_task_response(
                 "200",
                 "success",
-                Some(response::TaskResponseData::Task(result.unwrap())),
+                Some(response::TaskResponseData::Task(result)),
             );
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
+                .insert_header(etag)
                 .json(response)
         }
         Err(err) => {
-            error!("get task error: {:?}", err);
+            error!("create task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            let response = response::create_task_response("500", err.to_string().as_str(), None);
-            HttpResponse::Ok()
+pub async fn bulk_task(
+    req: HttpRequest,
+    bulk_request: Json<request::BulkRequest>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .bulk(bulk_request.into_inner(), user_id, x_ref_id.clone())
+        .await
+    {
+        // 200 when every operation succeeded, the code of the first failure
+        // when none did and 207 for a mix of both
+        Ok(results) => {
+            let status = match results.iter().filter(|result| result.is_ok()).count() {
+                count if count == results.len() => StatusCode::OK,
+                0 => results
+                    .iter()
+                    .filter_map(|result| result.as_ref().err())
+                    .map(|err| err.status_code())
+                    .find(|status| *status != StatusCode::FAILED_DEPENDENCY)
+                    .unwrap_or(StatusCode::FAILED_DEPENDENCY),
+                _ => StatusCode::MULTI_STATUS,
+            };
+            let description = match status {
+                StatusCode::OK => "success",
+                StatusCode::MULTI_STATUS => "some operations failed",
+                _ => "no operation was applied",
+            };
+            let response = response::create_bulk_response(status.as_str(), description, results);
+            HttpResponse::build(status)
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
+        Err(err) => {
+            error!("bulk task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
     }
 }
 
-pub async fn create_task(
+pub async fn update_task_by_id(
+    task_id: Path<String>,
     req: HttpRequest,
     task_request: Json<request::TaskRequest>,
-    data: Data<Mutex<TaskService>>,
+    service: Data<TaskService>,
 ) -> impl Responder {
-    let mut service = data.lock().unwrap();
-
     let x_ref_id = get_ref_id(req.headers());
-    let user_id = match get_user_id(req.headers()) {
+    let user_id = match get_user_id(&req) {
         Ok(user_id) => user_id,
-        Err(err) => {
-            error!("get user_id error: {:?}", err);
+        Err(err) => return error_response(err, x_ref_id),
+    };
+    let if_match = match get_if_match(&req) {
+        Ok(if_match) => if_match,
+        Err(err) => return error_response(err, x_ref_id),
+    };
 
-            return HttpResponse::Unauthorized()
+    match service
+        .update(
+            task_request.into_inner(),
+            task_id.to_string(),
+            user_id.to_string(),
+            if_match,
+            x_ref_id.clone(),
+        )
+        .await
+    {
+        Ok(result) => {
+            let etag = task_etag(&result.task);
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Task(result)),
+            );
+            HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
-                .finish();
+                .insert_header(etag)
+                .json(response)
+        }
+        Err(err) => {
+            error!("update task error: {:?}", err);
+            error_response(err, x_ref_id)
         }
+    }
+}
+
+pub async fn patch_task_by_id(
+    task_id: Path<String>,
+    req: HttpRequest,
+    task_patch: Json<request::TaskPatch>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+    let if_match = match get_if_match(&req) {
+        Ok(if_match) => if_match,
+        Err(err) => return error_response(err, x_ref_id),
     };
 
-    // service
-    match service.insert(task_request.into_inner(), user_id) {
+    match service
+        .patch(
+            task_patch.into_inner(),
+            task_id.to_string(),
+            user_id.to_string(),
+            if_match,
+            x_ref_id.clone(),
+        )
+        .await
+    {
         Ok(result) => {
+            let etag = task_etag(&result.task);
             let response = response::create_task_response(
                 "200",
                 "success",
@@ -119,45 +338,168 @@ pub async fn create_task(
             );
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
+                .insert_header(etag)
                 .json(response)
         }
         Err(err) => {
-            error!("get task error: {:?}", err);
+            error!("patch task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            let response = response::create_task_response("500", err.to_string().as_str(), None);
+pub async fn get_task_children(
+    task_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::TaskQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let mut query = query.into_inner();
+    query.tag = get_tags(&req);
+
+    match service
+        .find_children(task_id.to_string(), user_id.to_string(), query)
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
+        Err(err) => {
+            error!("get task children error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
     }
 }
 
-pub async fn update_task_by_id(
+pub async fn get_task_tree(
     task_id: Path<String>,
     req: HttpRequest,
-    task_request: Json<request::TaskRequest>,
-    data: Data<Mutex<TaskService>>,
+    service: Data<TaskService>,
 ) -> impl Responder {
-    let mut service = data.lock().unwrap();
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
 
+    match service
+        .find_tree(task_id.to_string(), user_id.to_string())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Tree(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("get task tree error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_task_occurrences(
+    task_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::OccurrenceQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
     let x_ref_id = get_ref_id(req.headers());
-    let user_id = match get_user_id(req.headers()) {
+    let user_id = match get_user_id(&req) {
         Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .find_occurrences(task_id.to_string(), user_id.to_string(), query.into_inner())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Occurrences(result)),
+            );
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
         Err(err) => {
-            error!("get user_id error: {:?}", err);
+            error!("get task occurrences error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            return HttpResponse::Unauthorized()
+pub async fn get_task_dependencies(
+    task_id: Path<String>,
+    req: HttpRequest,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .find_dependencies(task_id.to_string(), user_id.to_string())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Graph(result)),
+            );
+            HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
-                .finish();
+                .json(response)
         }
+        Err(err) => {
+            error!("get task dependencies error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn add_task_blocker(
+    task_id: Path<String>,
+    req: HttpRequest,
+    dependency_request: Json<request::DependencyRequest>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
     };
 
-    match service.update(
-        task_request.into_inner(),
-        task_id.to_string(),
-        user_id.to_string(),
-    ) {
+    match service
+        .add_blocker(
+            dependency_request.into_inner(),
+            task_id.to_string(),
+            user_id.to_string(),
+            x_ref_id.clone(),
+        )
+        .await
+    {
         Ok(result) => {
+            let etag = task_etag(&result.task);
             let response = response::create_task_response(
                 "200",
                 "success",
@@ -165,80 +507,293 @@ pub async fn update_task_by_id(
             );
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
+                .insert_header(etag)
                 .json(response)
         }
         Err(err) => {
-            error!("get task error: {:?}", err);
+            error!("add task blocker error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            let response = response::create_task_response("500", err.to_string().as_str(), None);
+pub async fn delete_task_blocker(
+    path: Path<(String, String)>,
+    req: HttpRequest,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    let (task_id, blocker_id) = path.into_inner();
+    match service
+        .remove_blocker(task_id, blocker_id, user_id.to_string(), x_ref_id.clone())
+        .await
+    {
+        Ok(result) => {
+            let etag = task_etag(&result.task);
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Task(result)),
+            );
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
+                .insert_header(etag)
                 .json(response)
         }
+        Err(err) => {
+            error!("delete task blocker error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
     }
 }
 
 pub async fn delete_task_by_id(
     task_id: Path<String>,
     req: HttpRequest,
-    data: Data<Mutex<TaskService>>,
+    query: Query<request::TaskDeleteQuery>,
+    service: Data<TaskService>,
 ) -> impl Responder {
-    let mut service = data.lock().unwrap();
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+    let if_match = match get_if_match(&req) {
+        Ok(if_match) => if_match,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .delete(
+            query.into_inner(),
+            task_id.to_string(),
+            user_id.to_string(),
+            if_match,
+            x_ref_id.clone(),
+        )
+        .await
+    {
+        Ok(_) => {
+            let response = response::create_task_response("200", "success", None);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
+        Err(err) => {
+            error!("delete task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
+pub async fn get_trash(
+    req: HttpRequest,
+    query: Query<request::PageQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
     let x_ref_id = get_ref_id(req.headers());
-    let user_id = match get_user_id(req.headers()) {
+    let user_id = match get_user_id(&req) {
         Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .find_trash(user_id.to_string(), query.into_inner())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_page_response("200", "success", result);
+            HttpResponse::Ok()
+                .insert_header(("x-ref-id", x_ref_id))
+                .json(response)
+        }
         Err(err) => {
-            error!("get user_id error: {:?}", err);
+            error!("get trash error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            return HttpResponse::Unauthorized()
+pub async fn restore_task_by_id(
+    task_id: Path<String>,
+    req: HttpRequest,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .restore(task_id.to_string(), user_id.to_string(), x_ref_id.clone())
+        .await
+    {
+        Ok(result) => {
+            let etag = task_etag(&result.task);
+            let response = response::create_task_response(
+                "200",
+                "success",
+                Some(response::TaskResponseData::Task(result)),
+            );
+            HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
-                .finish();
+                .insert_header(etag)
+                .json(response)
         }
+        Err(err) => {
+            error!("restore task error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
+
+pub async fn get_task_history(
+    task_id: Path<String>,
+    req: HttpRequest,
+    query: Query<request::PageQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
     };
 
-    match service.delete(task_id.to_string(), user_id.to_string()) {
-        Ok(_) => {
-            let response = response::create_task_response("200", "success", None);
+    match service
+        .find_history(task_id.to_string(), user_id.to_string(), query.into_inner())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_event_response("200", "success", result);
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
         Err(err) => {
-            error!("get task error: {:?}", err);
+            error!("get task history error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
+    }
+}
 
-            let response = response::create_task_response("500", err.to_string().as_str(), None);
+pub async fn get_activity(
+    req: HttpRequest,
+    query: Query<request::PageQuery>,
+    service: Data<TaskService>,
+) -> impl Responder {
+    let x_ref_id = get_ref_id(req.headers());
+    let user_id = match get_user_id(&req) {
+        Ok(user_id) => user_id,
+        Err(err) => return error_response(err, x_ref_id),
+    };
+
+    match service
+        .find_activity(user_id.to_string(), query.into_inner())
+        .await
+    {
+        Ok(result) => {
+            let response = response::create_task_event_response("200", "success", result);
             HttpResponse::Ok()
                 .insert_header(("x-ref-id", x_ref_id))
                 .json(response)
         }
+        Err(err) => {
+            error!("get activity error: {:?}", err);
+            error_response(err, x_ref_id)
+        }
     }
 }
 
-fn get_ref_id(header: &HeaderMap) -> String {
+pub fn error_response(err: AppError, x_ref_id: String) -> HttpResponse {
+    let mut response = err.error_response();
+    if let Ok(value) = HeaderValue::from_str(&x_ref_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static("x-ref-id"), value);
+    }
+
+    response
+}
+
+pub fn get_ref_id(header: &HeaderMap) -> String {
     let x_ref_id = header
         .get("x-ref-id")
         .map(|id| id.to_str().unwrap_or_default())
         .unwrap_or(uuid::Uuid::new_v4().to_string().as_str())
         .to_string();
 
-    return x_ref_id;
+    x_ref_id
 }
 
-fn get_user_id(header: &HeaderMap) -> Result<String, Box<dyn Error>> {
-    // let x_token = header
-    //     .get("x_token")
-    //     .map(|id| id.to_str().unwrap_or_default())
-    //     .unwrap_or_default()
-    //     .to_string();
+pub fn get_user_id(req: &HttpRequest) -> Result<String, AppError> {
+    // principal is injected by the authentication middleware
+    let user_id = req
+        .extensions()
+        .get::<Principal>()
+        .map(|principal| principal.user_id.clone())
+        .ok_or_else(|| AppError::Unauthorized("request is not authenticated".to_string()))?;
+    Ok(user_id)
+}
 
-    // // verify token
-    // let user_id = token::get_user_id(x_token.as_str())?;
+// an unreadable key is kept as empty and refused by the service
+pub fn get_idempotency_key(header: &HeaderMap) -> Option<String> {
+    header
+        .get("idempotency-key")
+        .map(|key| key.to_str().unwrap_or_default().to_string())
+}
 
-    let user_id = header
-        .get("user-id")
-        .map(|id| id.to_str().unwrap_or_default())
+// the task versions an If-Match header accepts, None when it is missing or *.
+// The comparison is strong, so weak tags never match
+pub fn get_if_match(req: &HttpRequest) -> Result<Option<Vec<i32>>, AppError> {
+    if !req.headers().contains_key(IfMatch::name()) {
+        return Ok(None);
+    }
+
+    // tags that cannot be parsed are dropped, a header without any is invalid
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => Ok(None),
+        Ok(IfMatch::Items(tags)) if !tags.is_empty() => Ok(Some(
+            tags.iter()
+                .filter(|tag| !tag.weak)
+                .filter_map(|tag| tag.tag().parse().ok())
+                .collect(),
+        )),
+        _ => Err(AppError::Validation(
+            "If-Match must be * or a list of entity tags".to_string(),
+        )),
+    }
+}
+
+// If-None-Match uses the weak comparison, an unreadable header never matches
+pub fn is_not_modified(req: &HttpRequest, etag: &ETag) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        Err(_) => false,
+    }
+}
+
+pub fn task_etag(task: &Task) -> ETag {
+    ETag(EntityTag::new_strong(task.version.to_string()))
+}
+
+// Query<TaskQuery> keeps a single value per key, so the repeated tag
+// parameters are read from the raw query string
+pub fn get_tags(req: &HttpRequest) -> Vec<String> {
+    Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(|params| {
+            params
+                .into_inner()
+                .into_iter()
+                .filter(|(key, _)| key == "tag")
+                .map(|(_, value)| value)
+                .collect()
+        })
         .unwrap_or_default()
-        .to_string();
-    Ok(user_id)
 }
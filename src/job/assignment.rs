@@ -0,0 +1,22 @@
+use crate::model::task_event::TaskAssigned;
+
+use log::{error, info};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+// logs every assignment once it is committed, other subscribers follow the same loop
+pub async fn log_assignments(mut assignments: Receiver<TaskAssigned>) {
+    loop {
+        match assignments.recv().await {
+            Ok(event) => info!(
+                "task {} assigned to {} by {}",
+                event.task_id,
+                event.assignee_id.as_deref().unwrap_or("nobody"),
+                event.actor
+            ),
+            Err(RecvError::Lagged(missed)) => {
+                error!("assignment log fell behind, {} events missed", missed)
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
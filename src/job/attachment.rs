@@ -0,0 +1,22 @@
+use crate::configuration::model::Attachment;
+use crate::service::attachment::AttachmentService;
+use crate::service::interface::AttachmentServiceInterface;
+
+use actix_web::{rt::time, web::Data};
+use log::{error, info};
+
+// deletes the blobs whose attachment is gone, be it deleted on its own or
+// purged with its task, once at start up and then every cleanup_interval seconds
+pub async fn delete_orphaned_blobs(service: Data<AttachmentService>, config: Attachment) {
+    let mut interval = time::interval(std::time::Duration::from_secs(config.cleanup_interval));
+
+    loop {
+        interval.tick().await;
+
+        match service.delete_orphaned_blobs().await {
+            Ok(0) => {}
+            Ok(deleted) => info!("deleted {} orphaned blobs", deleted),
+            Err(err) => error!("delete orphaned blobs error: {:?}", err),
+        }
+    }
+}
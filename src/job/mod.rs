@@ -0,0 +1,3 @@
+pub mod assignment;
+pub mod attachment;
+pub mod trash;
@@ -0,0 +1,24 @@
+use crate::configuration::model::Trash;
+use crate::service::interface::TaskServiceInterface;
+use crate::service::task_manager::TaskService;
+
+use actix_web::{rt::time, web::Data};
+use chrono::{Duration, Utc};
+use log::{error, info};
+
+// permanently deletes the tasks that have been in the trash for longer than
+// the retention period, once at start up and then every purge_interval seconds
+pub async fn purge_trash(service: Data<TaskService>, config: Trash) {
+    let mut interval = time::interval(std::time::Duration::from_secs(config.purge_interval));
+
+    loop {
+        interval.tick().await;
+
+        let trashed_before = Utc::now() - Duration::days(config.retention_days.into());
+        match service.purge_trash(trashed_before).await {
+            Ok(0) => {}
+            Ok(purged) => info!("purged {} tasks from the trash", purged),
+            Err(err) => error!("purge trash error: {:?}", err),
+        }
+    }
+}
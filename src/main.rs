@@ -1,22 +1,24 @@
 mod configuration;
 mod database;
+mod event;
 mod handler;
+mod job;
 mod middleware;
 mod model;
 mod repository;
 mod router;
 mod service;
+mod storage;
 mod util;
 
 use crate::configuration::config_yaml::load_config;
 use crate::configuration::model::AppConfig;
-use crate::router::task_manager::config_route;
 
 use actix_web::{middleware::Logger, web, App, HttpServer};
 use env_logger::Env;
 use log::{error, info};
-use std::sync::Mutex;
-use std::{io, process};
+use std::sync::Arc;
+use std::{env, io, process};
 // use tracing_subscriber;
 
 #[actix_web::main]
@@ -27,6 +29,16 @@ async fn main() -> std::io::Result<()> {
     // json log
     // tracing_subscriber::fmt().json().init();
 
+    // `task-manager migrate` applies pending migrations and exits
+    let migrate_only = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("migrate") => true,
+        Some(command) => {
+            error!("unknown command: {:}", command);
+            process::exit(1);
+        }
+    };
+
     let app_config: AppConfig = match load_config("config/config.yaml") {
         Ok(config) => {
             info!("config: {:?}", config);
@@ -48,25 +60,105 @@ async fn main() -> std::io::Result<()> {
     };
 
     // database
-    let db_connection =
-        database::postgres::connect_database(app_config.database).unwrap_or_else(|err| {
-            error!("connect database error: {:?}", err);
+    let run_migrations = app_config.database.run_migrations;
+    let db_pool = database::postgres::connect_database(app_config.database).unwrap_or_else(|err| {
+        error!("connect database error: {:?}", err);
+        process::exit(1);
+    });
+
+    // migrations
+    if migrate_only || run_migrations {
+        match database::migration::run_pending_migrations(&db_pool) {
+            Ok(versions) => info!("applied migrations: {:?}", versions),
+            Err(err) => {
+                error!("run migrations error: {:}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    if migrate_only {
+        return Ok(());
+    }
+
+    // authentication, refuse to start without key material
+    let token_verifier = match util::token::TokenVerifier::from_config(&app_config.auth) {
+        Ok(verifier) => Arc::new(verifier),
+        Err(err) => {
+            error!("load jwt key material error: {:}", err);
             process::exit(1);
-        });
+        }
+    };
+
+    // attachments, refuse to start without somewhere to keep them
+    let blob_storage = match storage::from_config(&app_config.attachment) {
+        Ok(storage) => storage,
+        Err(err) => {
+            error!("configure attachment storage error: {:}", err);
+            process::exit(1);
+        }
+    };
 
     // redis
     let mut redis_client = database::cache::Client::new(app_config.redis);
     redis_client
         .connect_redis()
+        .await
         .unwrap_or_else(|err| error!("connect redis error: {:}", err));
 
     // component
+    let tag_repository = repository::tag::TagRepository::new(db_pool.clone(), redis_client.clone());
+    let tag_service = service::tag::TagService::new(tag_repository);
+    let project_repository =
+        repository::project::ProjectRepository::new(db_pool.clone(), redis_client.clone());
+    let project_service = service::project::ProjectService::new(project_repository);
+    let share_repository = repository::share::ShareRepository::new(db_pool.clone());
+    let share_service = service::share::ShareService::new(share_repository);
+    let comment_repository = repository::comment::CommentRepository::new(db_pool.clone());
+    let comment_service = service::comment::CommentService::new(comment_repository);
+    let attachment_repository = repository::attachment::AttachmentRepository::new(db_pool.clone());
+    let attachment_service = service::attachment::AttachmentService::new(
+        attachment_repository,
+        blob_storage,
+        app_config.attachment.clone(),
+    );
+    let event_bus = event::bus::EventBus::new();
     let task_repository =
-        repository::task_manager::TaskRepository::new(db_connection, redis_client);
-    let task_service = service::task_manager::TaskService::new(task_repository);
+        repository::task_manager::TaskRepository::new(db_pool, redis_client, event_bus.clone());
+    let task_service = service::task_manager::TaskService::new(
+        task_repository,
+        app_config.task,
+        app_config.idempotency,
+    );
 
     // inject service to handler
-    let data_task_service = web::Data::new(Mutex::new(task_service));
+    let data_task_service = web::Data::new(task_service);
+    let data_tag_service = web::Data::new(tag_service);
+    let data_project_service = web::Data::new(project_service);
+    let data_share_service = web::Data::new(share_service);
+    let data_comment_service = web::Data::new(comment_service);
+    let data_attachment_service = web::Data::new(attachment_service);
+
+    // background jobs
+    if app_config.trash.purge_interval == 0 {
+        error!("trash purge_interval must be at least 1 second");
+        process::exit(1);
+    }
+    if app_config.attachment.cleanup_interval == 0 {
+        error!("attachment cleanup_interval must be at least 1 second");
+        process::exit(1);
+    }
+    actix_web::rt::spawn(job::assignment::log_assignments(
+        event_bus.subscribe_assignments(),
+    ));
+    actix_web::rt::spawn(job::trash::purge_trash(
+        web::Data::clone(&data_task_service),
+        app_config.trash,
+    ));
+    actix_web::rt::spawn(job::attachment::delete_orphaned_blobs(
+        web::Data::clone(&data_attachment_service),
+        app_config.attachment,
+    ));
 
     // start server
     info!(
@@ -75,12 +167,25 @@ async fn main() -> std::io::Result<()> {
     );
     HttpServer::new(move || {
         App::new()
-            .configure(config_route)
+            .configure(router::task_manager::config_route)
+            .configure(router::tag::config_route)
+            .configure(router::project::config_route)
+            .configure(router::share::config_route)
+            .configure(router::comment::config_route)
+            .configure(router::attachment::config_route)
+            .wrap(middleware::auth::Authentication {
+                verifier: Arc::clone(&token_verifier),
+            })
             .wrap(middleware::logger::Logger {})
             .wrap(Logger::new(
                 "timestamp: %t | method: %r | code: %s | latency: %D",
             ))
             .app_data(web::Data::clone(&data_task_service))
+            .app_data(web::Data::clone(&data_tag_service))
+            .app_data(web::Data::clone(&data_project_service))
+            .app_data(web::Data::clone(&data_share_service))
+            .app_data(web::Data::clone(&data_comment_service))
+            .app_data(web::Data::clone(&data_attachment_service))
     })
     .workers(4)
     .bind((
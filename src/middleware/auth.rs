@@ -0,0 +1,201 @@
+use crate::model::response;
+use crate::util::token::TokenVerifier;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header,
+    Error, HttpMessage, HttpResponse,
+};
+use futures_util::future::LocalBoxFuture;
+use log::error;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+const REALM: &str = "task-manager";
+
+pub struct Authentication {
+    pub verifier: Arc<TokenVerifier>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service,
+            verifier: Arc::clone(&self.verifier),
+        }))
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: S,
+    verifier: Arc<TokenVerifier>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let token = match bearer_token(&req) {
+            Some(token) => token,
+            None => {
+                let err = unauthorized(None, "missing bearer token");
+                return Box::pin(async move { Err(err) });
+            }
+        };
+
+        let principal = match self.verifier.verify(&token) {
+            Ok(principal) => principal,
+            Err(err) => {
+                error!("verify token error: {:}", err);
+
+                let err = unauthorized(Some("invalid_token"), err.to_string().as_str());
+                return Box::pin(async move { Err(err) });
+            }
+        };
+
+        req.extensions_mut().insert(principal);
+        let fut = self.service.call(req);
+
+        Box::pin(fut)
+    }
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+
+    Some(token.trim().to_string())
+}
+
+fn unauthorized(error_code: Option<&str>, description: &str) -> Error {
+    // RFC 6750: a request without credentials gets the bare challenge
+    let challenge = match error_code {
+        Some(error_code) => format!(
+            "Bearer realm=\"{}\", error=\"{}\", error_description=\"{}\"",
+            REALM,
+            error_code,
+            description.replace('"', "'")
+        ),
+        None => format!("Bearer realm=\"{}\"", REALM),
+    };
+
+    let body = response::create_task_response("401", description, None);
+    let response = HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, challenge))
+        .json(body);
+
+    InternalError::from_response(description.to_string(), response).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::model::Auth;
+    use crate::model::auth::Principal;
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::json;
+
+    fn verifier() -> Arc<TokenVerifier> {
+        let verifier = TokenVerifier::from_config(&Auth {
+            algorithm: "HS256".to_string(),
+            secret: Some("test-secret".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        Arc::new(verifier)
+    }
+
+    // the status and challenge of the request, with authorization as given
+    async fn challenge(authorization: Option<&str>) -> (StatusCode, Option<String>) {
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication {
+                    verifier: verifier(),
+                })
+                .route(
+                    "/",
+                    web::get().to(|principal: web::ReqData<Principal>| async move {
+                        HttpResponse::Ok().body(principal.user_id.clone())
+                    }),
+                ),
+        )
+        .await;
+
+        let mut request = test::TestRequest::get().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.insert_header((header::AUTHORIZATION, authorization));
+        }
+        let response = match test::try_call_service(&app, request.to_request()).await {
+            Ok(response) => response.map_into_boxed_body().into_parts().1,
+            Err(err) => err.as_response_error().error_response(),
+        };
+
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .map(|value| value.to_str().unwrap().to_string());
+        (response.status(), challenge)
+    }
+
+    #[actix_web::test]
+    async fn valid_token_passes() {
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &json!({ "sub": "alice", "exp": 4102444800u64 }),
+            &EncodingKey::from_secret(b"test-secret"),
+        )
+        .unwrap();
+
+        let (status, challenge) = challenge(Some(&format!("Bearer {}", token))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(challenge, None);
+    }
+
+    #[actix_web::test]
+    async fn missing_or_malformed_header_gets_the_bare_challenge() {
+        for authorization in [
+            None,
+            Some("Basic YWxpY2U6c2VjcmV0"),
+            Some("Bearer "),
+            Some("token"),
+        ] {
+            let (status, challenge) = challenge(authorization).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", authorization);
+            assert_eq!(challenge.as_deref(), Some("Bearer realm=\"task-manager\""));
+        }
+    }
+
+    #[actix_web::test]
+    async fn invalid_token_gets_an_invalid_token_challenge() {
+        let (status, challenge) = challenge(Some("Bearer not.a.token")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(challenge
+            .unwrap()
+            .starts_with("Bearer realm=\"task-manager\", error=\"invalid_token\""));
+    }
+}
@@ -20,7 +20,7 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(LoggerMiddleware { service: service }))
+        ready(Ok(LoggerMiddleware { service }))
     }
 }
 
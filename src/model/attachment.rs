@@ -0,0 +1,46 @@
+use crate::model::schema;
+use crate::storage::BlobStream;
+use chrono::{DateTime, Utc};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::attachment)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attachment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub uploader: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub checksum: String,
+    // where the bytes are kept is nobody's business but the storage's
+    #[serde(skip)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// a file as it arrives in a multipart body, name and type as the client sent them
+pub struct Upload {
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub body: BlobStream,
+}
+
+// the bytes of an attachment on their way out, all of them unless a range was asked for
+pub struct Download {
+    pub attachment: Attachment,
+    // first and last byte, inclusive
+    pub range: Option<(u64, u64)>,
+    pub body: BlobStream,
+}
+
+impl Attachment {
+    // the content never changes, so its hash is a strong validator
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.checksum)
+    }
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Principal {
+    pub user_id: String,
+}
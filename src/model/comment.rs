@@ -0,0 +1,45 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::comment)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Comment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub author: String,
+    // markdown, rendering is left to the clients
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// a user mentioned in a comment, kept until a notification picks it up
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::comment_mention)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CommentMention {
+    pub comment_id: Uuid,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub notified_at: Option<DateTime<Utc>>,
+}
+
+// a comment as returned to clients, with the users it mentions
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentDetail {
+    #[serde(flatten)]
+    pub comment: Comment,
+    pub mentions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentPage {
+    pub comments: Vec<CommentDetail>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,242 @@
+use crate::model::response;
+use actix_web::{
+    error::BlockingError,
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+use diesel::r2d2::PoolError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use redis::RedisError;
+use std::fmt::{self, Debug, Display};
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Validation(String),
+    Unauthorized(String),
+    Forbidden(String),
+    Conflict(String),
+    PreconditionFailed(String),
+    UnprocessableEntity(String),
+    FailedDependency(String),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    // the size of the resource the range was asked of
+    RangeNotSatisfiable(u64),
+    Storage(String),
+    Cache(String),
+}
+
+impl AppError {
+    // storage and cache details are logged, never returned to the client
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Validation(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::Conflict(message)
+            | AppError::PreconditionFailed(message)
+            | AppError::UnprocessableEntity(message)
+            | AppError::FailedDependency(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::UnsupportedMediaType(message) => message.clone(),
+            AppError::RangeNotSatisfiable(_) => "range not satisfiable".to_string(),
+            AppError::Storage(_) | AppError::Cache(_) => "internal server error".to_string(),
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message) => write!(f, "not found: {}", message),
+            AppError::Validation(message) => write!(f, "validation error: {}", message),
+            AppError::Unauthorized(message) => write!(f, "unauthorized: {}", message),
+            AppError::Forbidden(message) => write!(f, "forbidden: {}", message),
+            AppError::Conflict(message) => write!(f, "conflict: {}", message),
+            AppError::PreconditionFailed(message) => {
+                write!(f, "precondition failed: {}", message)
+            }
+            AppError::UnprocessableEntity(message) => {
+                write!(f, "unprocessable entity: {}", message)
+            }
+            AppError::FailedDependency(message) => write!(f, "failed dependency: {}", message),
+            AppError::PayloadTooLarge(message) => write!(f, "payload too large: {}", message),
+            AppError::UnsupportedMediaType(message) => {
+                write!(f, "unsupported media type: {}", message)
+            }
+            AppError::RangeNotSatisfiable(size) => {
+                write!(f, "range not satisfiable: size is {}", size)
+            }
+            AppError::Storage(message) => write!(f, "storage error: {}", message),
+            AppError::Cache(message) => write!(f, "cache error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::FailedDependency(_) => StatusCode::FAILED_DEPENDENCY,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            AppError::Storage(_) | AppError::Cache(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let body =
+            response::create_task_response(status.as_str(), self.public_message().as_str(), None);
+
+        let mut builder = HttpResponse::build(status);
+        match self {
+            AppError::Unauthorized(_) => {
+                builder.insert_header((header::WWW_AUTHENTICATE, "Bearer realm=\"task-manager\""));
+            }
+            AppError::RangeNotSatisfiable(size) => {
+                builder.insert_header((header::CONTENT_RANGE, format!("bytes */{}", size)));
+            }
+            _ => {}
+        }
+
+        builder.json(body)
+    }
+}
+
+impl From<DieselError> for AppError {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => AppError::NotFound("record not found".to_string()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                AppError::Conflict("record already exists".to_string())
+            }
+            // postgres says which side of the reference the write was on
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info)
+                if info.message().starts_with("insert or update") =>
+            {
+                AppError::Validation("a referenced record does not exist".to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                AppError::Conflict("record is still referenced".to_string())
+            }
+            err => AppError::Storage(err.to_string()),
+        }
+    }
+}
+
+impl From<PoolError> for AppError {
+    fn from(err: PoolError) -> Self {
+        AppError::Storage(err.to_string())
+    }
+}
+
+impl From<BlockingError> for AppError {
+    fn from(err: BlockingError) -> Self {
+        AppError::Storage(err.to_string())
+    }
+}
+
+impl From<RedisError> for AppError {
+    fn from(err: RedisError) -> Self {
+        AppError::Cache(err.to_string())
+    }
+}
+
+// json is encoded and decoded around what is stored, the cache maps its own errors
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Storage(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        AppError::Storage(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::result::DatabaseErrorInformation;
+
+    struct Information(&'static str);
+
+    impl DatabaseErrorInformation for Information {
+        fn message(&self) -> &str {
+            self.0
+        }
+        fn details(&self) -> Option<&str> {
+            None
+        }
+        fn hint(&self) -> Option<&str> {
+            None
+        }
+        fn table_name(&self) -> Option<&str> {
+            None
+        }
+        fn column_name(&self) -> Option<&str> {
+            None
+        }
+        fn constraint_name(&self) -> Option<&str> {
+            None
+        }
+        fn statement_position(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    fn database_error(kind: DatabaseErrorKind, message: &'static str) -> AppError {
+        DieselError::DatabaseError(kind, Box::new(Information(message))).into()
+    }
+
+    #[test]
+    fn foreign_key_violations_are_client_errors() {
+        let err = database_error(
+            DatabaseErrorKind::ForeignKeyViolation,
+            "insert or update on table \"task\" violates foreign key constraint \"task_project_id_fkey\"",
+        );
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        let err = database_error(
+            DatabaseErrorKind::ForeignKeyViolation,
+            "update or delete on table \"project\" violates foreign key constraint \"task_project_id_fkey\" on table \"task\"",
+        );
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn unique_violation_is_a_conflict() {
+        let err = database_error(DatabaseErrorKind::UniqueViolation, "duplicate key");
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn json_errors_are_not_cache_errors() {
+        let err: AppError = serde_json::from_str::<u32>("x").unwrap_err().into();
+        assert!(matches!(err, AppError::Storage(_)));
+        assert_eq!(err.public_message(), "internal server error");
+    }
+
+    #[test]
+    fn unsatisfiable_range_carries_the_size() {
+        let response = AppError::RangeNotSatisfiable(3000).error_response();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */3000"
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::idempotency_key)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IdempotencyKey {
+    pub owner: String,
+    pub key: String,
+    // sha-256 of the request, a retry has to send the same one
+    pub request_hash: String,
+    // the response of the first request, replayed on every retry
+    pub response: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -1,4 +1,13 @@
+pub mod attachment;
+pub mod auth;
+pub mod comment;
+pub mod error;
+pub mod idempotency;
+pub mod project;
 pub mod request;
 pub mod response;
 pub mod schema;
+pub mod share;
+pub mod tag;
+pub mod task_event;
 pub mod task_manager;
@@ -0,0 +1,27 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::prelude::{AsChangeset, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, AsChangeset, Insertable)]
+#[diesel(table_name = schema::project)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Project {
+    pub id: Uuid,
+    pub owner: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// what happens to the tasks of a deleted project
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectDeleteMode {
+    Inbox,
+    Cascade,
+}
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::Debug;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -6,4 +6,177 @@ pub struct TaskRequest {
     pub title: String,
     pub description: String,
     pub completed: bool,
+    pub due_at: Option<String>,
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub project_id: Option<String>,
+    pub parent_id: Option<String>,
+    pub auto_complete: Option<bool>,
+    pub recurrence: Option<String>,
+    pub assignee_id: Option<String>,
+}
+
+// JSON Merge Patch (RFC 7396): an absent member is left untouched and a
+// null member is removed, so every field keeps both states apart
+#[derive(Deserialize, Debug, Default)]
+pub struct TaskPatch {
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub completed: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub due_at: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub priority: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub tags: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub project_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub parent_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub auto_complete: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub recurrence: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_patch")]
+    pub assignee_id: Option<Option<String>>,
+}
+
+fn deserialize_patch<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TaskQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub completed: Option<bool>,
+    pub title: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    // repeated tag parameters are collected by the handler
+    #[serde(skip)]
+    pub tag: Vec<String>,
+    pub tag_match: Option<String>,
+    pub project_id: Option<String>,
+    // me, none or the id of a user
+    pub assignee: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TaskSearchQuery {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub completed: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PageQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BulkRequest {
+    // atomic applies every operation or none, partial applies the ones that succeed
+    pub mode: Option<String>,
+    pub operations: Vec<BulkOperation>,
+}
+
+// version makes the operation conditional, like If-Match on a single task
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Create {
+        task: TaskRequest,
+    },
+    Update {
+        id: String,
+        task: TaskPatch,
+        version: Option<i32>,
+    },
+    Complete {
+        id: String,
+        version: Option<i32>,
+    },
+    Delete {
+        id: String,
+        children: Option<String>,
+        version: Option<i32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DependencyRequest {
+    pub blocker_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TaskDeleteQuery {
+    pub children: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OccurrenceQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DueQuery {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagRequest {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ProjectDeleteQuery {
+    pub mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareRequest {
+    pub user_id: String,
+    pub role: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareRoleRequest {
+    pub role: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentRequest {
+    pub body: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_keeps_absent_null_and_values_apart() {
+        let task_patch: TaskPatch =
+            serde_json::from_str(r#"{"title":"renamed","due_at":null}"#).unwrap();
+        assert_eq!(task_patch.title, Some(Some("renamed".to_string())));
+        assert_eq!(task_patch.due_at, Some(None));
+        assert_eq!(task_patch.project_id, None);
+        assert_eq!(task_patch.parent_id, None);
+    }
 }
@@ -1,20 +1,60 @@
+use actix_web::ResponseError;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-use super::task_manager::Task;
+use super::attachment::Attachment;
+use super::comment::{CommentDetail, CommentPage};
+use super::project::Project;
+use super::share::Share;
+use super::tag::Tag;
+use super::task_event::{TaskEvent, TaskEventPage};
+use super::task_manager::{
+    TaskDetail, TaskGraph, TaskPage, TaskSearchPage, TaskSearchResult, TaskTree, TaskWriteResult,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TaskResponse {
     pub code: String,
     pub description: String,
     pub data: Option<TaskResponseData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum TaskResponseData {
-    Task(Task),
-    Tasks(Vec<Task>),
+    Task(TaskDetail),
+    Tasks(Vec<TaskDetail>),
+    SearchResults(Vec<TaskSearchResult>),
+    Tree(TaskTree),
+    Graph(TaskGraph),
+    Occurrences(Vec<DateTime<Utc>>),
+    Events(Vec<TaskEvent>),
+    Bulk(Vec<BulkItemResponse>),
+    Tag(Tag),
+    Tags(Vec<Tag>),
+    Project(Project),
+    Projects(Vec<Project>),
+    Share(Share),
+    Shares(Vec<Share>),
+    Comment(CommentDetail),
+    Comments(Vec<CommentDetail>),
+    Attachment(Attachment),
+    Attachments(Vec<Attachment>),
+}
+
+// the outcome of one operation of a batch, with the code it would have had on its own
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BulkItemResponse {
+    pub code: String,
+    pub description: String,
+    pub data: Option<TaskDetail>,
 }
 
 pub fn create_task_response(
@@ -25,6 +65,116 @@ pub fn create_task_response(
     TaskResponse {
         code: String::from(code),
         description: String::from(description),
-        data: data,
+        data,
+        total: None,
+        next_cursor: None,
+        has_more: None,
+    }
+}
+
+pub fn create_task_page_response(code: &str, description: &str, page: TaskPage) -> TaskResponse {
+    TaskResponse {
+        code: String::from(code),
+        description: String::from(description),
+        data: Some(TaskResponseData::Tasks(page.tasks)),
+        total: Some(page.total),
+        has_more: Some(page.next_cursor.is_some()),
+        next_cursor: page.next_cursor,
+    }
+}
+
+pub fn create_task_search_response(
+    code: &str,
+    description: &str,
+    page: TaskSearchPage,
+) -> TaskResponse {
+    TaskResponse {
+        code: String::from(code),
+        description: String::from(description),
+        data: Some(TaskResponseData::SearchResults(page.results)),
+        total: Some(page.total),
+        has_more: Some(page.next_cursor.is_some()),
+        next_cursor: page.next_cursor,
+    }
+}
+
+pub fn create_task_event_response(
+    code: &str,
+    description: &str,
+    page: TaskEventPage,
+) -> TaskResponse {
+    TaskResponse {
+        code: String::from(code),
+        description: String::from(description),
+        data: Some(TaskResponseData::Events(page.events)),
+        total: Some(page.total),
+        has_more: Some(page.next_cursor.is_some()),
+        next_cursor: page.next_cursor,
+    }
+}
+
+pub fn create_comment_page_response(
+    code: &str,
+    description: &str,
+    page: CommentPage,
+) -> TaskResponse {
+    TaskResponse {
+        code: String::from(code),
+        description: String::from(description),
+        data: Some(TaskResponseData::Comments(page.comments)),
+        total: Some(page.total),
+        has_more: Some(page.next_cursor.is_some()),
+        next_cursor: page.next_cursor,
+    }
+}
+
+pub fn create_bulk_response(
+    code: &str,
+    description: &str,
+    results: Vec<TaskWriteResult>,
+) -> TaskResponse {
+    let items = results
+        .into_iter()
+        .map(|result| match result {
+            Ok(data) => BulkItemResponse {
+                code: String::from("200"),
+                description: String::from("success"),
+                data,
+            },
+            Err(err) => BulkItemResponse {
+                code: String::from(err.status_code().as_str()),
+                description: err.public_message(),
+                data: None,
+            },
+        })
+        .collect();
+
+    create_task_response(code, description, Some(TaskResponseData::Bulk(items)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn page_envelope(next_cursor: Option<&str>) -> Value {
+        let page = TaskPage {
+            tasks: Vec::new(),
+            total: 25,
+            next_cursor: next_cursor.map(str::to_string),
+        };
+        serde_json::to_value(create_task_page_response("200", "ok", page)).unwrap()
+    }
+
+    #[test]
+    fn page_envelope_carries_the_cursor() {
+        let envelope = page_envelope(Some("10"));
+        assert_eq!(envelope["total"], 25);
+        assert_eq!(envelope["has_more"], true);
+        assert_eq!(envelope["next_cursor"], "10");
+
+        let envelope = page_envelope(None);
+        assert_eq!(envelope["has_more"], false);
+        assert!(envelope.get("next_cursor").is_none());
     }
 }
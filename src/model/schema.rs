@@ -1,9 +1,181 @@
+pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "share_role"))]
+    pub struct ShareRole;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "task_action"))]
+    pub struct TaskAction;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "task_priority"))]
+    pub struct TaskPriority;
+}
+
+diesel::table! {
+    attachment (id) {
+        id -> Uuid,
+        task_id -> Uuid,
+        uploader -> Text,
+        file_name -> Text,
+        content_type -> Text,
+        size -> Int8,
+        checksum -> Text,
+        storage_key -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comment (id) {
+        id -> Uuid,
+        task_id -> Uuid,
+        author -> Text,
+        body -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comment_mention (comment_id, user_id) {
+        comment_id -> Uuid,
+        user_id -> Text,
+        created_at -> Timestamptz,
+        notified_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    idempotency_key (owner, key) {
+        owner -> Text,
+        key -> Text,
+        request_hash -> Text,
+        response -> Jsonb,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    orphaned_blob (storage_key) {
+        storage_key -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    project (id) {
+        id -> Uuid,
+        owner -> Text,
+        name -> Text,
+        description -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ShareRole;
+
+    share (id) {
+        id -> Uuid,
+        user_id -> Text,
+        task_id -> Nullable<Uuid>,
+        project_id -> Nullable<Uuid>,
+        role -> ShareRole,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
+    tag (id) {
+        id -> Uuid,
+        owner -> Text,
+        name -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::TaskPriority;
+
     task (id) {
-        id -> Text,
+        id -> Uuid,
         title -> Text,
         description -> Text,
         completed -> Bool,
-        owner -> Text
+        owner -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        due_at -> Nullable<Timestamptz>,
+        priority -> TaskPriority,
+        project_id -> Nullable<Uuid>,
+        parent_id -> Nullable<Uuid>,
+        auto_complete -> Bool,
+        recurrence -> Nullable<Text>,
+        recurrence_start -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        version -> Int4,
+        assignee_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    task_dependency (task_id, blocker_id) {
+        task_id -> Uuid,
+        blocker_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::TaskAction;
+
+    task_event (id) {
+        id -> Uuid,
+        task_id -> Uuid,
+        owner -> Text,
+        actor -> Text,
+        action -> TaskAction,
+        ref_id -> Nullable<Text>,
+        changes -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    task_tag (task_id, tag_id) {
+        task_id -> Uuid,
+        tag_id -> Uuid,
     }
 }
+
+diesel::joinable!(attachment -> task (task_id));
+diesel::joinable!(comment -> task (task_id));
+diesel::joinable!(comment_mention -> comment (comment_id));
+diesel::joinable!(share -> project (project_id));
+diesel::joinable!(share -> task (task_id));
+diesel::joinable!(task -> project (project_id));
+diesel::joinable!(task_tag -> tag (tag_id));
+diesel::joinable!(task_tag -> task (task_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    attachment,
+    comment,
+    comment_mention,
+    idempotency_key,
+    orphaned_blob,
+    project,
+    share,
+    tag,
+    task,
+    task_dependency,
+    task_event,
+    task_tag,
+);
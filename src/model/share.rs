@@ -0,0 +1,143 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Display};
+use std::io::Write;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// a role granted to another user on a task, or on every task of a project
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::share)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Share {
+    pub id: Uuid,
+    pub user_id: String,
+    pub task_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub role: ShareRole,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// what is shared, a task or a project with all of its tasks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareTarget {
+    Task(Uuid),
+    Project(Uuid),
+}
+
+impl ShareTarget {
+    pub fn new(kind: ShareKind, target_id: Uuid) -> Self {
+        match kind {
+            ShareKind::Task => ShareTarget::Task(target_id),
+            ShareKind::Project => ShareTarget::Project(target_id),
+        }
+    }
+}
+
+impl Display for ShareTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareTarget::Task(task_id) => write!(f, "task {}", task_id),
+            ShareTarget::Project(project_id) => write!(f, "project {}", project_id),
+        }
+    }
+}
+
+// the kind of target a share request is made on, the id is validated by the service
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareKind {
+    Task,
+    Project,
+}
+
+impl ShareKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareKind::Task => "task",
+            ShareKind::Project => "project",
+        }
+    }
+}
+
+// each role can do everything the roles before it can, the owner of a task
+// or project always has the owner role
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    AsExpression,
+    FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[diesel(sql_type = schema::sql_types::ShareRole)]
+pub enum ShareRole {
+    // reads the tasks
+    #[default]
+    Viewer,
+    // also writes them and adds tasks to a shared project
+    Editor,
+    // also deletes and restores them and shares them with others
+    Owner,
+}
+
+impl ShareRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareRole::Viewer => "viewer",
+            ShareRole::Editor => "editor",
+            ShareRole::Owner => "owner",
+        }
+    }
+
+    // the roles that include this one
+    pub fn and_above(&self) -> Vec<ShareRole> {
+        [ShareRole::Viewer, ShareRole::Editor, ShareRole::Owner]
+            .into_iter()
+            .filter(|role| role >= self)
+            .collect()
+    }
+}
+
+impl FromStr for ShareRole {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(ShareRole::Viewer),
+            "editor" => Ok(ShareRole::Editor),
+            "owner" => Ok(ShareRole::Owner),
+            _ => Err(format!(
+                "role must be one of viewer, editor, owner but got {}",
+                value
+            )),
+        }
+    }
+}
+
+impl ToSql<schema::sql_types::ShareRole, Pg> for ShareRole {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<schema::sql_types::ShareRole, Pg> for ShareRole {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(ShareRole::from_str(value)?)
+    }
+}
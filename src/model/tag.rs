@@ -0,0 +1,23 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::prelude::{AsChangeset, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, AsChangeset, Insertable)]
+#[diesel(table_name = schema::tag)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Tag {
+    pub id: Uuid,
+    pub owner: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Queryable, Insertable)]
+#[diesel(table_name = schema::task_tag)]
+pub struct TaskTag {
+    pub task_id: Uuid,
+    pub tag_id: Uuid,
+}
@@ -0,0 +1,130 @@
+use crate::model::schema;
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::io::Write;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::task_event)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TaskEvent {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub owner: String,
+    // the user who made the change, system for changes made by background jobs
+    pub actor: String,
+    pub action: TaskAction,
+    // x-ref-id of the request that made the change
+    pub ref_id: Option<String>,
+    // the changed fields as {"field": {"old": ..., "new": ...}}
+    pub changes: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+// who makes a change and in which request, recorded on its events
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor: String,
+    pub ref_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn new(actor: &str, ref_id: String) -> Self {
+        AuditContext {
+            actor: actor.to_string(),
+            ref_id: Some(ref_id),
+        }
+    }
+
+    pub fn system() -> Self {
+        AuditContext {
+            actor: "system".to_string(),
+            ref_id: None,
+        }
+    }
+}
+
+// published once a change of assignee is committed, for other parts of the
+// service to react to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskAssigned {
+    pub task_id: Uuid,
+    pub owner: String,
+    pub actor: String,
+    pub previous_assignee_id: Option<String>,
+    pub assignee_id: Option<String>,
+    pub ref_id: Option<String>,
+    pub assigned_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskEventPage {
+    pub events: Vec<TaskEvent>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, AsExpression, FromSqlRow)]
+#[serde(rename_all = "snake_case")]
+#[diesel(sql_type = schema::sql_types::TaskAction)]
+pub enum TaskAction {
+    Created,
+    Updated,
+    // moved to the trash
+    Deleted,
+    Restored,
+    // removed from the trash for good
+    Purged,
+    // given to another assignee, along with any other change made with it
+    Assigned,
+}
+
+impl TaskAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskAction::Created => "created",
+            TaskAction::Updated => "updated",
+            TaskAction::Deleted => "deleted",
+            TaskAction::Restored => "restored",
+            TaskAction::Purged => "purged",
+            TaskAction::Assigned => "assigned",
+        }
+    }
+}
+
+impl FromStr for TaskAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "created" => Ok(TaskAction::Created),
+            "updated" => Ok(TaskAction::Updated),
+            "deleted" => Ok(TaskAction::Deleted),
+            "restored" => Ok(TaskAction::Restored),
+            "purged" => Ok(TaskAction::Purged),
+            "assigned" => Ok(TaskAction::Assigned),
+            _ => Err(format!("unknown task action {}", value)),
+        }
+    }
+}
+
+impl ToSql<schema::sql_types::TaskAction, Pg> for TaskAction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<schema::sql_types::TaskAction, Pg> for TaskAction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(TaskAction::from_str(value)?)
+    }
+}
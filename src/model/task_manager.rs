@@ -1,17 +1,278 @@
+use crate::model::error::AppError;
 use crate::model::schema;
-use diesel::prelude::{AsChangeset, Insertable, Queryable, Selectable};
+use crate::model::share::ShareRole;
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::{AsChangeset, Insertable, Queryable, QueryableByName, Selectable};
+use diesel::serialize::{self, IsNull, Output, ToSql};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::io::Write;
+use std::str::FromStr;
+use uuid::Uuid;
 
 #[derive(
-    Serialize, Deserialize, Debug, Queryable, Selectable, AsChangeset, Insertable, Default,
+    Serialize,
+    Deserialize,
+    Debug,
+    Queryable,
+    QueryableByName,
+    Selectable,
+    AsChangeset,
+    Insertable,
+    Default,
 )]
 #[diesel(table_name = schema::task)]
+#[diesel(treat_none_as_null = true)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Task {
-    pub id: String,
+    pub id: Uuid,
     pub title: String,
     pub description: String,
     pub completed: bool,
     pub owner: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub project_id: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    // completes the task once all of its subtasks are completed
+    pub auto_complete: bool,
+    // an RRULE, the task is replaced by its next occurrence when completed
+    pub recurrence: Option<String>,
+    pub recurrence_start: Option<DateTime<Utc>>,
+    // set while the task is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
+    // incremented on every write, sent to clients as the ETag
+    pub version: i32,
+    // the user working on the task, who can access it as owner or through a share
+    pub assignee_id: Option<String>,
+}
+
+// a task as returned to clients, together with the rows it owns in other tables
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TaskDetail {
+    #[serde(flatten)]
+    pub task: Task,
+    pub tags: Vec<String>,
+    // at least one of the tasks blocking it is still open
+    pub blocked: bool,
+    // the role of the user reading the task, owner for their own tasks
+    pub role: ShareRole,
+    // set when completing the task created its next occurrence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_occurrence_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = schema::task_dependency)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TaskDependency {
+    pub task_id: Uuid,
+    pub blocker_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+// a task with every task blocking it, directly or through other blockers
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskGraph {
+    pub tasks: Vec<TaskDetail>,
+    pub dependencies: Vec<TaskDependency>,
+}
+
+// a task with its subtasks nested below it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskTree {
+    #[serde(flatten)]
+    pub task: TaskDetail,
+    pub children: Vec<TaskTree>,
+}
+
+// what happens to the subtasks of a deleted task
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtaskDeleteMode {
+    #[default]
+    Cascade,
+    // subtasks move up to the parent of the deleted task
+    Promote,
+}
+
+// one write of a batch, prepared by the service and applied by the repository
+#[derive(Debug)]
+pub enum TaskWrite {
+    Insert {
+        task: Task,
+        tags: Vec<String>,
+    },
+    Update {
+        task: Task,
+        tags: Option<Vec<String>>,
+        next_occurrence: Option<Box<Task>>,
+    },
+    Delete {
+        task_id: Uuid,
+        mode: SubtaskDeleteMode,
+        expected_versions: Option<Vec<i32>>,
+    },
+}
+
+// the task as written, None for a delete
+pub type TaskWriteResult = Result<Option<TaskDetail>, AppError>;
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, AsExpression, FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[diesel(sql_type = schema::sql_types::TaskPriority)]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            _ => Err(format!(
+                "priority must be one of low, medium, high, urgent but got {}",
+                value
+            )),
+        }
+    }
+}
+
+impl ToSql<schema::sql_types::TaskPriority, Pg> for Priority {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<schema::sql_types::TaskPriority, Pg> for Priority {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(Priority::from_str(value)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSort {
+    Id,
+    Title,
+    CreatedAt,
+    UpdatedAt,
+    CompletedAt,
+    DueAt,
+    Priority,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    Any,
+    All,
+}
+
+// whose tasks are listed, by the user they are assigned to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssigneeScope {
+    Unassigned,
+    User(String),
+}
+
+// which list the tasks are taken from, an owner's inbox holds the tasks without a project
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectScope {
+    Inbox,
+    Project(Uuid),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskFilter {
+    pub completed: Option<bool>,
+    pub title: Option<String>,
+    pub due_after: Option<DateTime<Utc>>,
+    pub due_before: Option<DateTime<Utc>>,
+    pub overdue: bool,
+    pub project: Option<ProjectScope>,
+    pub parent_id: Option<Uuid>,
+    pub assignee: Option<AssigneeScope>,
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    pub sort: TaskSort,
+    pub order: SortOrder,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskPage {
+    pub tasks: Vec<TaskDetail>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+// every term must match, either as a word or as the start of a longer one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskSearch {
+    pub terms: Vec<String>,
+    pub completed: Option<bool>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskSearchResult {
+    #[serde(flatten)]
+    pub task: TaskDetail,
+    pub rank: f32,
+    pub highlight: TaskHighlight,
+}
+
+// title and description excerpts with the matched words wrapped in <mark>
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskHighlight {
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskSearchPage {
+    pub results: Vec<TaskSearchResult>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
 }
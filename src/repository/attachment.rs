@@ -0,0 +1,190 @@
+use crate::database::postgres::{self, PgPool};
+use crate::model::attachment::Attachment;
+use crate::model::error::AppError;
+use crate::model::schema::{self, attachment::dsl::*};
+use crate::model::share::ShareRole;
+use crate::model::task_manager::Task;
+use crate::repository::interface::AttachmentRepositoryInterface;
+use crate::repository::task_manager::require_role;
+use async_trait::async_trait;
+use diesel::{
+    delete, insert_into, Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+// only the metadata is kept here, the bytes are in blob storage
+pub struct AttachmentRepository {
+    db_pool: PgPool,
+}
+
+impl AttachmentRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        AttachmentRepository { db_pool }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepositoryInterface for AttachmentRepository {
+    async fn check_upload(
+        &self,
+        attachment_task_id: Uuid,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            find_task(connection, attachment_task_id, &user_id, ShareRole::Editor)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn insert(&self, new_attachment: Attachment) -> Result<Attachment, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                // the task may have been trashed or unshared while the file was uploading
+                find_task(
+                    connection,
+                    new_attachment.task_id,
+                    &new_attachment.uploader,
+                    ShareRole::Editor,
+                )?;
+
+                insert_into(attachment)
+                    .values(&new_attachment)
+                    .execute(connection)?;
+
+                Ok(new_attachment)
+            })
+        })
+        .await
+    }
+
+    async fn find_all(
+        &self,
+        attachment_task_id: Uuid,
+        user_id: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            find_task(connection, attachment_task_id, &user_id, ShareRole::Viewer)?;
+
+            let attachments = attachment
+                .filter(task_id.eq(attachment_task_id))
+                .order((created_at.asc(), id.asc()))
+                .select(Attachment::as_select())
+                .load(connection)?;
+
+            Ok(attachments)
+        })
+        .await
+    }
+
+    async fn find_by_id(
+        &self,
+        attachment_id: Uuid,
+        attachment_task_id: Uuid,
+        user_id: String,
+    ) -> Result<Attachment, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            find_task(connection, attachment_task_id, &user_id, ShareRole::Viewer)?;
+
+            attachment
+                .filter(id.eq(attachment_id))
+                .filter(task_id.eq(attachment_task_id))
+                .select(Attachment::as_select())
+                .first(connection)
+                .optional()?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("attachment {} not found", attachment_id))
+                })
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        attachment_id: Uuid,
+        attachment_task_id: Uuid,
+        user_id: String,
+    ) -> Result<String, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let task_row =
+                    find_task(connection, attachment_task_id, &user_id, ShareRole::Viewer)?;
+                let current: Attachment = attachment
+                    .filter(id.eq(attachment_id))
+                    .filter(task_id.eq(attachment_task_id))
+                    .select(Attachment::as_select())
+                    .for_update()
+                    .first(connection)
+                    .optional()?
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("attachment {} not found", attachment_id))
+                    })?;
+
+                if current.uploader != user_id {
+                    require_role(connection, &user_id, &task_row, ShareRole::Owner).map_err(
+                        |_| {
+                            AppError::Forbidden(format!(
+                                "only the uploader or the owner of the task can delete attachment {}",
+                                attachment_id
+                            ))
+                        },
+                    )?;
+                }
+
+                // the trigger queues the blob for deletion along with the row
+                delete(attachment)
+                    .filter(id.eq(attachment_id))
+                    .execute(connection)?;
+
+                Ok(current.storage_key)
+            })
+        })
+        .await
+    }
+
+    async fn find_orphaned_blobs(&self, limit: i64) -> Result<Vec<String>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let keys = schema::orphaned_blob::table
+                .order(schema::orphaned_blob::created_at.asc())
+                .limit(limit)
+                .select(schema::orphaned_blob::storage_key)
+                .load(connection)?;
+
+            Ok(keys)
+        })
+        .await
+    }
+
+    async fn delete_orphaned_blobs(&self, keys: Vec<String>) -> Result<(), AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            delete(schema::orphaned_blob::table)
+                .filter(schema::orphaned_blob::storage_key.eq_any(&keys))
+                .execute(connection)?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+// attachments are only read and written while the task is out of the trash,
+// the share lock keeps it from being trashed meanwhile
+fn find_task(
+    connection: &mut PgConnection,
+    attachment_task_id: Uuid,
+    user_id: &str,
+    role: ShareRole,
+) -> Result<Task, AppError> {
+    let task_row = schema::task::table
+        .filter(schema::task::id.eq(attachment_task_id))
+        .filter(schema::task::deleted_at.is_null())
+        .select(Task::as_select())
+        .for_share()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("task {} not found", attachment_task_id)))?;
+    require_role(connection, user_id, &task_row, role)?;
+
+    Ok(task_row)
+}
@@ -0,0 +1,265 @@
+use crate::database::postgres::{self, PgPool};
+use crate::model::comment::{Comment, CommentDetail, CommentMention, CommentPage};
+use crate::model::error::AppError;
+use crate::model::schema::{self, comment::dsl::*};
+use crate::model::share::ShareRole;
+use crate::model::task_manager::Task;
+use crate::repository::interface::CommentRepositoryInterface;
+use crate::repository::task_manager::{next_cursor, require_role};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::{
+    delete, insert_into, update, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// comments are read from postgres only, shared tasks are not cached for
+// their members so there would be nobody to invalidate a cached thread for
+pub struct CommentRepository {
+    db_pool: PgPool,
+}
+
+impl CommentRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        CommentRepository { db_pool }
+    }
+}
+
+#[async_trait]
+impl CommentRepositoryInterface for CommentRepository {
+    async fn insert(
+        &self,
+        new_comment: Comment,
+        mentions: Vec<String>,
+    ) -> Result<CommentDetail, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                // everyone who can read the task can discuss it
+                find_task(connection, new_comment.task_id, &new_comment.author)?;
+
+                insert_into(comment)
+                    .values(&new_comment)
+                    .execute(connection)?;
+                let mentions = set_mentions(
+                    connection,
+                    new_comment.id,
+                    &mentions,
+                    new_comment.created_at,
+                )?;
+
+                Ok(CommentDetail {
+                    comment: new_comment,
+                    mentions,
+                })
+            })
+        })
+        .await
+    }
+
+    async fn find_all(
+        &self,
+        comment_task_id: Uuid,
+        user_id: String,
+        offset: i64,
+        limit: i64,
+    ) -> Result<CommentPage, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            find_task(connection, comment_task_id, &user_id)?;
+
+            let total: i64 = comment
+                .filter(task_id.eq(comment_task_id))
+                .count()
+                .get_result(connection)?;
+
+            // oldest first, a thread reads top to bottom
+            let comments: Vec<Comment> = comment
+                .filter(task_id.eq(comment_task_id))
+                .order((created_at.asc(), id.asc()))
+                .offset(offset)
+                .limit(limit)
+                .select(Comment::as_select())
+                .load(connection)?;
+
+            let comment_ids: Vec<Uuid> = comments.iter().map(|row| row.id).collect();
+            let mut mentions = load_mentions(connection, &comment_ids)?;
+            let comments: Vec<CommentDetail> = comments
+                .into_iter()
+                .map(|row| CommentDetail {
+                    mentions: mentions.remove(&row.id).unwrap_or_default(),
+                    comment: row,
+                })
+                .collect();
+
+            Ok(CommentPage {
+                next_cursor: next_cursor(offset, comments.len(), total),
+                comments,
+                total,
+            })
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        comment_id: Uuid,
+        comment_task_id: Uuid,
+        new_body: String,
+        mentions: Vec<String>,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                find_task(connection, comment_task_id, &user_id)?;
+                let current = lock_comment(connection, comment_id, comment_task_id)?;
+
+                // not even the owner of the task can put words in someone's mouth
+                if current.author != user_id {
+                    return Err(AppError::Forbidden(format!(
+                        "only the author can edit comment {}",
+                        comment_id
+                    )));
+                }
+
+                let now = Utc::now();
+                let result = update(comment)
+                    .filter(id.eq(comment_id))
+                    .set((body.eq(&new_body), updated_at.eq(now)))
+                    .returning(Comment::as_returning())
+                    .get_result(connection)?;
+                let mentions = set_mentions(connection, comment_id, &mentions, now)?;
+
+                Ok(CommentDetail {
+                    comment: result,
+                    mentions,
+                })
+            })
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        comment_id: Uuid,
+        comment_task_id: Uuid,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let task_row = find_task(connection, comment_task_id, &user_id)?;
+                let current = lock_comment(connection, comment_id, comment_task_id)?;
+
+                // the owner of the task can moderate its thread
+                if current.author != user_id {
+                    require_role(connection, &user_id, &task_row, ShareRole::Owner).map_err(
+                        |_| {
+                            AppError::Forbidden(format!(
+                                "only the author or the owner of the task can delete comment {}",
+                                comment_id
+                            ))
+                        },
+                    )?;
+                }
+
+                // the mentions go with the comment
+                delete(comment)
+                    .filter(id.eq(comment_id))
+                    .execute(connection)?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+}
+
+// comments are only read and written while the task is out of the trash,
+// the share lock keeps it from being trashed while its thread is written
+fn find_task(
+    connection: &mut PgConnection,
+    comment_task_id: Uuid,
+    user_id: &str,
+) -> Result<Task, AppError> {
+    let task_row = schema::task::table
+        .filter(schema::task::id.eq(comment_task_id))
+        .filter(schema::task::deleted_at.is_null())
+        .select(Task::as_select())
+        .for_share()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("task {} not found", comment_task_id)))?;
+    require_role(connection, user_id, &task_row, ShareRole::Viewer)?;
+
+    Ok(task_row)
+}
+
+fn lock_comment(
+    connection: &mut PgConnection,
+    comment_id: Uuid,
+    comment_task_id: Uuid,
+) -> Result<Comment, AppError> {
+    comment
+        .filter(id.eq(comment_id))
+        .filter(task_id.eq(comment_task_id))
+        .select(Comment::as_select())
+        .for_update()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("comment {} not found", comment_id)))
+}
+
+// replaces the mentions of a comment, a user still mentioned after an edit
+// keeps their row so they are not notified twice. Returns them in order of name
+fn set_mentions(
+    connection: &mut PgConnection,
+    comment_id: Uuid,
+    mentions: &[String],
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, AppError> {
+    delete(schema::comment_mention::table)
+        .filter(schema::comment_mention::comment_id.eq(comment_id))
+        .filter(schema::comment_mention::user_id.ne_all(mentions))
+        .execute(connection)?;
+
+    let rows: Vec<CommentMention> = mentions
+        .iter()
+        .map(|mention| CommentMention {
+            comment_id,
+            user_id: mention.clone(),
+            created_at: now,
+            notified_at: None,
+        })
+        .collect();
+    if !rows.is_empty() {
+        insert_into(schema::comment_mention::table)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+
+    let mut mentions = mentions.to_vec();
+    mentions.sort();
+    Ok(mentions)
+}
+
+fn load_mentions(
+    connection: &mut PgConnection,
+    comment_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, AppError> {
+    let rows: Vec<(Uuid, String)> = schema::comment_mention::table
+        .filter(schema::comment_mention::comment_id.eq_any(comment_ids))
+        .order(schema::comment_mention::user_id.asc())
+        .select((
+            schema::comment_mention::comment_id,
+            schema::comment_mention::user_id,
+        ))
+        .load(connection)?;
+
+    let mut mentions: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (comment_id, mention) in rows {
+        mentions.entry(comment_id).or_default().push(mention);
+    }
+
+    Ok(mentions)
+}
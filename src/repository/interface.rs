@@ -1,14 +1,217 @@
-use crate::model::task_manager::Task;
-use std::error::Error;
+use crate::model::attachment::Attachment;
+use crate::model::comment::{Comment, CommentDetail, CommentPage};
+use crate::model::error::AppError;
+use crate::model::idempotency::IdempotencyKey;
+use crate::model::project::{Project, ProjectDeleteMode};
+use crate::model::share::{Share, ShareRole, ShareTarget};
+use crate::model::tag::Tag;
+use crate::model::task_event::{AuditContext, TaskEventPage};
+use crate::model::task_manager::{
+    SubtaskDeleteMode, Task, TaskDependency, TaskDetail, TaskFilter, TaskPage, TaskSearch,
+    TaskSearchPage, TaskWrite, TaskWriteResult,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
+#[async_trait]
 pub trait TaskRepositoryInterface {
-    fn insert(&mut self, task: Task) -> Result<Task, Box<dyn Error>>;
-    fn find_all(&mut self, user_id: String) -> Result<Vec<Task>, Box<dyn Error>>;
-    fn find_by_id(
-        &mut self,
-        task_id: String,
-        user_id: String,
-    ) -> Result<Option<Task>, Box<dyn Error>>;
-    fn update(&mut self, update_task: Task) -> Result<Task, Box<dyn Error>>;
-    fn delete(&mut self, task_id: String, user_id: String) -> Result<(), Box<dyn Error>>;
+    async fn insert(
+        &self,
+        task: Task,
+        tags: Vec<String>,
+        idempotency_key: Option<IdempotencyKey>,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError>;
+    async fn bulk(
+        &self,
+        user_id: String,
+        writes: Vec<TaskWrite>,
+        atomic: bool,
+        context: AuditContext,
+    ) -> Result<Vec<TaskWriteResult>, AppError>;
+    async fn find_idempotency_key(
+        &self,
+        user_id: String,
+        key: String,
+    ) -> Result<Option<IdempotencyKey>, AppError>;
+    async fn find_all(&self, user_id: String, filter: TaskFilter) -> Result<TaskPage, AppError>;
+    async fn search(&self, user_id: String, search: TaskSearch)
+        -> Result<TaskSearchPage, AppError>;
+    async fn find_by_id(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<Option<TaskDetail>, AppError>;
+    async fn find_lineage(&self, task_id: Uuid, user_id: String) -> Result<Vec<Uuid>, AppError>;
+    async fn find_tree(&self, task_id: Uuid, user_id: String) -> Result<Vec<TaskDetail>, AppError>;
+    async fn find_by_ids(
+        &self,
+        task_ids: Vec<Uuid>,
+        user_id: String,
+    ) -> Result<Vec<TaskDetail>, AppError>;
+    async fn find_dependencies(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        include_trashed: bool,
+    ) -> Result<Vec<TaskDependency>, AppError>;
+    async fn find_open_blockers(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<Vec<Uuid>, AppError>;
+    async fn add_dependency(
+        &self,
+        task_id: Uuid,
+        blocker_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError>;
+    async fn remove_dependency(
+        &self,
+        task_id: Uuid,
+        blocker_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError>;
+    async fn update(
+        &self,
+        update_task: Task,
+        user_id: String,
+        tags: Option<Vec<String>>,
+        next_occurrence: Option<Task>,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError>;
+    async fn delete(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        mode: SubtaskDeleteMode,
+        expected_versions: Option<Vec<i32>>,
+        context: AuditContext,
+    ) -> Result<(), AppError>;
+    async fn find_trash(
+        &self,
+        user_id: String,
+        offset: i64,
+        limit: i64,
+    ) -> Result<TaskPage, AppError>;
+    async fn restore(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError>;
+    async fn purge(&self, trashed_before: DateTime<Utc>) -> Result<usize, AppError>;
+    async fn find_events(
+        &self,
+        user_id: String,
+        task_id: Option<Uuid>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<TaskEventPage, AppError>;
+}
+
+#[async_trait]
+pub trait TagRepositoryInterface {
+    async fn insert(&self, tag: Tag) -> Result<Tag, AppError>;
+    async fn find_all(&self, user_id: String) -> Result<Vec<Tag>, AppError>;
+    async fn find_by_id(&self, tag_id: Uuid, user_id: String) -> Result<Option<Tag>, AppError>;
+    async fn update(&self, update_tag: Tag) -> Result<Tag, AppError>;
+    async fn delete(&self, tag_id: Uuid, user_id: String) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ProjectRepositoryInterface {
+    async fn insert(&self, project: Project) -> Result<Project, AppError>;
+    async fn find_all(&self, user_id: String) -> Result<Vec<Project>, AppError>;
+    async fn find_by_id(
+        &self,
+        project_id: Uuid,
+        user_id: String,
+    ) -> Result<Option<Project>, AppError>;
+    async fn update(&self, update_project: Project) -> Result<Project, AppError>;
+    async fn delete(
+        &self,
+        project_id: Uuid,
+        user_id: String,
+        mode: ProjectDeleteMode,
+        context: AuditContext,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ShareRepositoryInterface {
+    async fn insert(
+        &self,
+        target: ShareTarget,
+        share: Share,
+        user_id: String,
+    ) -> Result<Share, AppError>;
+    async fn find_all(&self, target: ShareTarget, user_id: String) -> Result<Vec<Share>, AppError>;
+    async fn update(
+        &self,
+        target: ShareTarget,
+        member: String,
+        role: ShareRole,
+        user_id: String,
+    ) -> Result<Share, AppError>;
+    async fn delete(
+        &self,
+        target: ShareTarget,
+        member: String,
+        user_id: String,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait CommentRepositoryInterface {
+    async fn insert(
+        &self,
+        comment: Comment,
+        mentions: Vec<String>,
+    ) -> Result<CommentDetail, AppError>;
+    async fn find_all(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        offset: i64,
+        limit: i64,
+    ) -> Result<CommentPage, AppError>;
+    async fn update(
+        &self,
+        comment_id: Uuid,
+        task_id: Uuid,
+        body: String,
+        mentions: Vec<String>,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError>;
+    async fn delete(
+        &self,
+        comment_id: Uuid,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait AttachmentRepositoryInterface {
+    async fn check_upload(&self, task_id: Uuid, user_id: String) -> Result<(), AppError>;
+    async fn insert(&self, attachment: Attachment) -> Result<Attachment, AppError>;
+    async fn find_all(&self, task_id: Uuid, user_id: String) -> Result<Vec<Attachment>, AppError>;
+    async fn find_by_id(
+        &self,
+        attachment_id: Uuid,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<Attachment, AppError>;
+    async fn delete(
+        &self,
+        attachment_id: Uuid,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<String, AppError>;
+    async fn find_orphaned_blobs(&self, limit: i64) -> Result<Vec<String>, AppError>;
+    async fn delete_orphaned_blobs(&self, storage_keys: Vec<String>) -> Result<(), AppError>;
 }
@@ -0,0 +1,199 @@
+use crate::database::cache::Client;
+use crate::database::postgres::{self, PgPool};
+use crate::model::error::AppError;
+use crate::model::project::{Project, ProjectDeleteMode};
+use crate::model::schema::{self, project::dsl::*};
+use crate::model::task_event::{AuditContext, TaskAction};
+use crate::repository::interface::ProjectRepositoryInterface;
+use crate::repository::task_event::{load_states, record_changes};
+use crate::repository::task_manager::{invalidate_tasks, trash_tasks};
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::pg::Pg;
+use diesel::sql_types;
+use diesel::{
+    delete, insert_into, update, BoolExpressionMethods, Connection, ExpressionMethods,
+    NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+pub struct ProjectRepository {
+    db_pool: PgPool,
+    redis_client: Client,
+}
+
+impl ProjectRepository {
+    pub fn new(db_pool: PgPool, redis_client: Client) -> Self {
+        ProjectRepository {
+            db_pool,
+            redis_client,
+        }
+    }
+}
+
+#[async_trait]
+impl ProjectRepositoryInterface for ProjectRepository {
+    async fn insert(&self, new_project: Project) -> Result<Project, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            insert_into(project)
+                .values(&new_project)
+                .execute(connection)?;
+
+            Ok(new_project)
+        })
+        .await
+    }
+
+    async fn find_all(&self, user_id: String) -> Result<Vec<Project>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let result = project
+                .filter(
+                    owner
+                        .eq(&user_id)
+                        .or(id.nullable().eq_any(shared_with(&user_id))),
+                )
+                .order((name.asc(), id.asc()))
+                .select(Project::as_select())
+                .load(connection)?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn find_by_id(
+        &self,
+        project_id: Uuid,
+        user_id: String,
+    ) -> Result<Option<Project>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let result = project
+                .filter(id.eq(project_id))
+                .filter(
+                    owner
+                        .eq(&user_id)
+                        .or(id.nullable().eq_any(shared_with(&user_id))),
+                )
+                .select(Project::as_select())
+                .first(connection)
+                .optional()?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn update(&self, update_project: Project) -> Result<Project, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let affected = update(project)
+                .filter(id.eq(&update_project.id))
+                .filter(owner.eq(&update_project.owner))
+                .set(&update_project)
+                .execute(connection)?;
+
+            if affected == 0 {
+                return Err(AppError::NotFound(format!(
+                    "project {} not found",
+                    update_project.id
+                )));
+            }
+
+            Ok(update_project)
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        project_id: Uuid,
+        user_id: String,
+        mode: ProjectDeleteMode,
+        context: AuditContext,
+    ) -> Result<(), AppError> {
+        let deleted_owner = user_id.clone();
+        let task_ids = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                // lock the project so no task can be moved into it meanwhile
+                let project_owner = project
+                    .filter(id.eq(project_id))
+                    .filter(
+                        owner
+                            .eq(&deleted_owner)
+                            .or(id.nullable().eq_any(shared_with(&deleted_owner))),
+                    )
+                    .select(owner)
+                    .for_update()
+                    .first::<String>(connection)
+                    .optional()?
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("project {} not found", project_id))
+                    })?;
+                if project_owner != deleted_owner {
+                    return Err(AppError::Forbidden(format!(
+                        "only the owner can delete project {}",
+                        project_id
+                    )));
+                }
+
+                let project_tasks = schema::task::table
+                    .filter(schema::task::project_id.eq(project_id))
+                    .filter(schema::task::owner.eq(&deleted_owner));
+
+                let task_ids: Vec<Uuid> = match mode {
+                    // the tasks go to the trash and are restored to the inbox
+                    ProjectDeleteMode::Cascade => {
+                        let project_task_ids: Vec<Uuid> = project_tasks
+                            .filter(schema::task::deleted_at.is_null())
+                            .select(schema::task::id)
+                            .load(connection)?;
+                        trash_tasks(connection, &project_task_ids, Utc::now(), &context)?
+                    }
+                    ProjectDeleteMode::Inbox => {
+                        let project_task_ids: Vec<Uuid> =
+                            project_tasks.select(schema::task::id).load(connection)?;
+                        let before = load_states(connection, &project_task_ids)?;
+
+                        update(schema::task::table)
+                            .filter(schema::task::id.eq_any(&project_task_ids))
+                            .set((
+                                schema::task::project_id.eq(None::<Uuid>),
+                                schema::task::updated_at.eq(Utc::now()),
+                                schema::task::version.eq(schema::task::version + 1),
+                            ))
+                            .execute(connection)?;
+                        record_changes(
+                            connection,
+                            &project_task_ids,
+                            &before,
+                            TaskAction::Updated,
+                            &context,
+                        )?;
+
+                        project_task_ids
+                    }
+                };
+
+                delete(project)
+                    .filter(id.eq(project_id))
+                    .execute(connection)?;
+
+                Ok(task_ids)
+            })
+        })
+        .await?;
+
+        invalidate_tasks(&self.redis_client, &user_id, &task_ids).await;
+
+        Ok(())
+    }
+}
+
+// ids of the projects shared with the user, in any role
+fn shared_with(
+    user_id: &str,
+) -> schema::share::BoxedQuery<'_, Pg, sql_types::Nullable<sql_types::Uuid>> {
+    schema::share::table
+        .filter(schema::share::user_id.eq(user_id))
+        .select(schema::share::project_id)
+        .into_boxed()
+}
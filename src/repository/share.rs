@@ -0,0 +1,224 @@
+use crate::database::postgres::{self, PgPool};
+use crate::model::error::AppError;
+use crate::model::schema;
+use crate::model::share::{Share, ShareRole, ShareTarget};
+use crate::model::task_manager::Task;
+use crate::repository::interface::ShareRepositoryInterface;
+use crate::repository::task_manager::load_roles;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::pg::Pg;
+use diesel::{
+    delete, insert_into, update, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use std::slice;
+use uuid::Uuid;
+
+// members only ever read shared tasks from postgres, so shares have nothing to invalidate
+pub struct ShareRepository {
+    db_pool: PgPool,
+}
+
+impl ShareRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        ShareRepository { db_pool }
+    }
+}
+
+#[async_trait]
+impl ShareRepositoryInterface for ShareRepository {
+    async fn insert(
+        &self,
+        target: ShareTarget,
+        new_share: Share,
+        user_id: String,
+    ) -> Result<Share, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let target_owner = require_owner(connection, target, &user_id)?;
+                if new_share.user_id == target_owner {
+                    return Err(AppError::Validation(format!(
+                        "{} is owned by {}",
+                        target, new_share.user_id
+                    )));
+                }
+
+                let existing = target_shares(target)
+                    .filter(schema::share::user_id.eq(&new_share.user_id))
+                    .select(schema::share::id)
+                    .first::<Uuid>(connection)
+                    .optional()?;
+                if existing.is_some() {
+                    return Err(AppError::Conflict(format!(
+                        "{} is already shared with {}",
+                        target, new_share.user_id
+                    )));
+                }
+
+                insert_into(schema::share::table)
+                    .values(&new_share)
+                    .execute(connection)?;
+
+                Ok(new_share)
+            })
+        })
+        .await
+    }
+
+    async fn find_all(&self, target: ShareTarget, user_id: String) -> Result<Vec<Share>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            // everyone who can see the target can see who else can
+            find_role(connection, target, &user_id)?;
+
+            let result = target_shares(target)
+                .order((schema::share::created_at.asc(), schema::share::id.asc()))
+                .select(Share::as_select())
+                .load(connection)?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        target: ShareTarget,
+        member: String,
+        role: ShareRole,
+        user_id: String,
+    ) -> Result<Share, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                require_owner(connection, target, &user_id)?;
+                let share = find_share(connection, target, &member)?;
+
+                let result = update(schema::share::table)
+                    .filter(schema::share::id.eq(share.id))
+                    .set((
+                        schema::share::role.eq(role),
+                        schema::share::updated_at.eq(Utc::now()),
+                    ))
+                    .returning(Share::as_returning())
+                    .get_result(connection)?;
+
+                Ok(result)
+            })
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        target: ShareTarget,
+        member: String,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                // members can always leave, only owners can remove others
+                if member == user_id {
+                    find_role(connection, target, &user_id)?;
+                } else {
+                    require_owner(connection, target, &user_id)?;
+                }
+                let share = find_share(connection, target, &member)?;
+
+                delete(schema::share::table)
+                    .filter(schema::share::id.eq(share.id))
+                    .execute(connection)?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+}
+
+fn target_shares(target: ShareTarget) -> schema::share::BoxedQuery<'static, Pg> {
+    let query = schema::share::table.into_boxed();
+    match target {
+        ShareTarget::Task(task_id) => query.filter(schema::share::task_id.eq(task_id)),
+        ShareTarget::Project(project_id) => query.filter(schema::share::project_id.eq(project_id)),
+    }
+}
+
+fn find_share(
+    connection: &mut PgConnection,
+    target: ShareTarget,
+    member: &str,
+) -> Result<Share, AppError> {
+    target_shares(target)
+        .filter(schema::share::user_id.eq(member))
+        .select(Share::as_select())
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("{} is not shared with {}", target, member)))
+}
+
+// the owner of the target and the role of the user on it, a target the user
+// cannot see is not found. The target is locked so it is not deleted meanwhile
+fn find_role(
+    connection: &mut PgConnection,
+    target: ShareTarget,
+    user_id: &str,
+) -> Result<(String, ShareRole), AppError> {
+    let not_found = || AppError::NotFound(format!("{} not found", target));
+
+    match target {
+        ShareTarget::Task(task_id) => {
+            let shared = schema::task::table
+                .filter(schema::task::id.eq(task_id))
+                .filter(schema::task::deleted_at.is_null())
+                .select(Task::as_select())
+                .for_share()
+                .first(connection)
+                .optional()?
+                .ok_or_else(not_found)?;
+            let role = load_roles(connection, user_id, slice::from_ref(&shared))?
+                .remove(&task_id)
+                .ok_or_else(not_found)?;
+
+            Ok((shared.owner, role))
+        }
+        ShareTarget::Project(project_id) => {
+            let project_owner = schema::project::table
+                .filter(schema::project::id.eq(project_id))
+                .select(schema::project::owner)
+                .for_share()
+                .first::<String>(connection)
+                .optional()?
+                .ok_or_else(not_found)?;
+            if project_owner == user_id {
+                return Ok((project_owner, ShareRole::Owner));
+            }
+
+            let role = target_shares(target)
+                .filter(schema::share::user_id.eq(user_id))
+                .select(schema::share::role)
+                .first::<ShareRole>(connection)
+                .optional()?
+                .ok_or_else(not_found)?;
+
+            Ok((project_owner, role))
+        }
+    }
+}
+
+// returns the owner of the target when the user may share it
+fn require_owner(
+    connection: &mut PgConnection,
+    target: ShareTarget,
+    user_id: &str,
+) -> Result<String, AppError> {
+    let (target_owner, role) = find_role(connection, target, user_id)?;
+    if role < ShareRole::Owner {
+        return Err(AppError::Forbidden(format!(
+            "{} is shared with you as {}",
+            target,
+            role.as_str()
+        )));
+    }
+
+    Ok(target_owner)
+}
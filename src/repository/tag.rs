@@ -0,0 +1,134 @@
+use crate::database::cache::Client;
+use crate::database::postgres::{self, PgPool};
+use crate::model::error::AppError;
+use crate::model::schema::{self, tag::dsl::*};
+use crate::model::tag::Tag;
+use crate::repository::interface::TagRepositoryInterface;
+use crate::repository::task_manager::{bump_versions, invalidate_tasks};
+use async_trait::async_trait;
+use diesel::{
+    delete, insert_into, update, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+pub struct TagRepository {
+    db_pool: PgPool,
+    redis_client: Client,
+}
+
+impl TagRepository {
+    pub fn new(db_pool: PgPool, redis_client: Client) -> Self {
+        TagRepository {
+            db_pool,
+            redis_client,
+        }
+    }
+}
+
+#[async_trait]
+impl TagRepositoryInterface for TagRepository {
+    async fn insert(&self, new_tag: Tag) -> Result<Tag, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            insert_into(tag).values(&new_tag).execute(connection)?;
+
+            Ok(new_tag)
+        })
+        .await
+    }
+
+    async fn find_all(&self, user_id: String) -> Result<Vec<Tag>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let result = tag
+                .filter(owner.eq(user_id))
+                .order(name.asc())
+                .select(Tag::as_select())
+                .load(connection)?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn find_by_id(&self, tag_id: Uuid, user_id: String) -> Result<Option<Tag>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let result = tag
+                .filter(id.eq(tag_id))
+                .filter(owner.eq(user_id))
+                .select(Tag::as_select())
+                .first(connection)
+                .optional()?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn update(&self, update_tag: Tag) -> Result<Tag, AppError> {
+        let (result, task_ids) = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let affected = update(tag)
+                    .filter(id.eq(&update_tag.id))
+                    .filter(owner.eq(&update_tag.owner))
+                    .set(name.eq(&update_tag.name))
+                    .execute(connection)?;
+
+                if affected == 0 {
+                    return Err(AppError::NotFound(format!(
+                        "tag {} not found",
+                        update_tag.id
+                    )));
+                }
+
+                // the tag name is part of every task carrying it, so their
+                // ETags have to change with it
+                let task_ids = tagged_task_ids(connection, update_tag.id)?;
+                bump_versions(connection, &task_ids)?;
+                Ok((update_tag, task_ids))
+            })
+        })
+        .await?;
+
+        // tag names are returned inline with tasks, so the cached tasks
+        // carrying the tag are stale too
+        invalidate_tasks(&self.redis_client, &result.owner, &task_ids).await;
+
+        Ok(result)
+    }
+
+    async fn delete(&self, tag_id: Uuid, user_id: String) -> Result<(), AppError> {
+        let deleted_owner = user_id.clone();
+        let task_ids = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                // read the tagged tasks before the cascade removes the links
+                let task_ids = tagged_task_ids(connection, tag_id)?;
+
+                let affected = delete(tag)
+                    .filter(id.eq(tag_id))
+                    .filter(owner.eq(deleted_owner))
+                    .execute(connection)?;
+
+                if affected == 0 {
+                    return Err(AppError::NotFound(format!("tag {} not found", tag_id)));
+                }
+                bump_versions(connection, &task_ids)?;
+
+                Ok(task_ids)
+            })
+        })
+        .await?;
+
+        invalidate_tasks(&self.redis_client, &user_id, &task_ids).await;
+
+        Ok(())
+    }
+}
+
+fn tagged_task_ids(connection: &mut PgConnection, tag_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    let task_ids = schema::task_tag::table
+        .filter(schema::task_tag::tag_id.eq(tag_id))
+        .select(schema::task_tag::task_id)
+        .load(connection)?;
+
+    Ok(task_ids)
+}
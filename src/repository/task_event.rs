@@ -0,0 +1,130 @@
+use crate::model::error::AppError;
+use crate::model::schema;
+use crate::model::task_event::{AuditContext, TaskAction, TaskEvent};
+use crate::model::task_manager::Task;
+use crate::repository::task_manager::load_tags;
+use chrono::Utc;
+use diesel::{
+    insert_into, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// the recorded state of tasks by id, with the owner the events are filed under
+pub type TaskStates = HashMap<Uuid, (String, Value)>;
+
+// reads the state of the given tasks, their rows with their tags and blockers,
+// and locks the rows so the state cannot change before the events are written
+pub fn load_states(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+) -> Result<TaskStates, AppError> {
+    let tasks: Vec<Task> = schema::task::table
+        .filter(schema::task::id.eq_any(task_ids))
+        .select(Task::as_select())
+        .for_update()
+        .load(connection)?;
+    let mut tags = load_tags(connection, task_ids)?;
+
+    let dependencies: Vec<(Uuid, Uuid)> = schema::task_dependency::table
+        .filter(schema::task_dependency::task_id.eq_any(task_ids))
+        .order(schema::task_dependency::blocker_id.asc())
+        .select((
+            schema::task_dependency::task_id,
+            schema::task_dependency::blocker_id,
+        ))
+        .load(connection)?;
+    let mut blockers: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (task_id, blocker_id) in dependencies {
+        blockers.entry(task_id).or_default().push(blocker_id);
+    }
+
+    let mut states = TaskStates::new();
+    for task_row in tasks {
+        let mut state = serde_json::to_value(&task_row)?;
+        if let Some(fields) = state.as_object_mut() {
+            // updated_at and version change with every write, the event has its own time
+            fields.remove("updated_at");
+            fields.remove("version");
+            fields.insert(
+                "tags".to_string(),
+                json!(tags.remove(&task_row.id).unwrap_or_default()),
+            );
+            fields.insert(
+                "blocker_ids".to_string(),
+                json!(blockers.remove(&task_row.id).unwrap_or_default()),
+            );
+        }
+        states.insert(task_row.id, (task_row.owner, state));
+    }
+
+    Ok(states)
+}
+
+// records an event for every given task whose state differs from the one read
+// before the change, a task missing from before has all of its fields recorded
+pub fn record_changes(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+    before: &TaskStates,
+    action: TaskAction,
+    context: &AuditContext,
+) -> Result<(), AppError> {
+    let after = load_states(connection, task_ids)?;
+    let now = Utc::now();
+
+    let events: Vec<TaskEvent> = after
+        .into_iter()
+        .filter_map(|(task_id, (owner, state))| {
+            let changes = diff(before.get(&task_id).map(|(_, state)| state), &state);
+            // saving a task without changing it is not an event
+            if changes.is_empty() && action == TaskAction::Updated {
+                return None;
+            }
+
+            Some(TaskEvent {
+                id: Uuid::new_v4(),
+                task_id,
+                owner,
+                actor: context.actor.clone(),
+                action,
+                ref_id: context.ref_id.clone(),
+                changes: Value::Object(changes),
+                created_at: now,
+            })
+        })
+        .collect();
+
+    insert_events(connection, &events)
+}
+
+pub fn insert_events(connection: &mut PgConnection, events: &[TaskEvent]) -> Result<(), AppError> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    insert_into(schema::task_event::table)
+        .values(events)
+        .execute(connection)?;
+
+    Ok(())
+}
+
+fn diff(before: Option<&Value>, after: &Value) -> Map<String, Value> {
+    let mut changes = Map::new();
+    let Some(after) = after.as_object() else {
+        return changes;
+    };
+
+    for (field, new_value) in after {
+        let old_value = before
+            .and_then(|before| before.get(field))
+            .unwrap_or(&Value::Null);
+        if old_value != new_value {
+            changes.insert(field.clone(), json!({ "old": old_value, "new": new_value }));
+        }
+    }
+
+    changes
+}
@@ -1,126 +1,2145 @@
 use crate::database::cache::Client;
-use crate::model::schema::task::dsl::*;
-use crate::model::task_manager::Task;
+use crate::database::postgres::{self, PgPool};
+use crate::event::bus::EventBus;
+use crate::model::error::AppError;
+use crate::model::idempotency::IdempotencyKey;
+use crate::model::schema::{self, task::dsl::*};
+use crate::model::share::ShareRole;
+use crate::model::tag::{Tag, TaskTag};
+use crate::model::task_event::{AuditContext, TaskAction, TaskAssigned, TaskEvent, TaskEventPage};
+use crate::model::task_manager::{
+    AssigneeScope, ProjectScope, SortOrder, SubtaskDeleteMode, TagMatch, Task, TaskDependency,
+    TaskDetail, TaskFilter, TaskHighlight, TaskPage, TaskSearch, TaskSearchPage, TaskSearchResult,
+    TaskSort, TaskWrite, TaskWriteResult,
+};
 use crate::repository::interface::TaskRepositoryInterface;
+use crate::repository::task_event::{insert_events, load_states, record_changes, TaskStates};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::{count_star, exists};
+use diesel::pg::Pg;
+use diesel::result::Error as DieselError;
+use diesel::sql_types;
 use diesel::{
-    delete, insert_into, update, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    delete, insert_into, select, sql_query, update, BoolExpressionMethods, BoxableExpression,
+    Connection, ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection,
+    PgSortExpressionMethods, PgTextExpressionMethods, QueryDsl, QueryableByName, RunQueryDsl,
     SelectableHelper,
 };
 use log::error;
-use std::error::Error;
+use std::collections::{HashMap, HashSet};
+use std::slice;
+use uuid::Uuid;
+
+// a write as applied within a batch, see apply_write
+type WriteOutcome = (Option<TaskDetail>, String, Vec<Uuid>, Option<TaskAssigned>);
 
 pub struct TaskRepository {
-    db_connection: PgConnection,
+    db_pool: PgPool,
     redis_client: Client,
+    events: EventBus,
 }
 
 impl TaskRepository {
-    pub fn new(db_connection: PgConnection, redis_client: Client) -> Self {
+    pub fn new(db_pool: PgPool, redis_client: Client, events: EventBus) -> Self {
         TaskRepository {
-            db_connection,
+            db_pool,
             redis_client,
+            events,
         }
     }
+
+    async fn invalidate(&self, user_id: &str, task_id: Option<Uuid>) {
+        invalidate_tasks(&self.redis_client, user_id, task_id.as_slice()).await;
+    }
 }
 
+// task and list keys embed a version, so bumping the version orphans every
+// cached copy at once and the orphans expire with their ttl. Versions are
+// bumped after the write commits and read before the row is, so a read that
+// raced the write can only cache the old row under a version already retired
+pub async fn invalidate_tasks(redis_client: &Client, user_id: &str, task_ids: &[Uuid]) {
+    for task_id in task_ids {
+        redis_client
+            .increment(task_version_key(user_id, *task_id))
+            .await
+            .map(|_| ())
+            .unwrap_or_else(|err| error!("increment task version in redis error: {:}", err));
+    }
+
+    redis_client
+        .increment(list_version_key(user_id))
+        .await
+        .map(|_| ())
+        .unwrap_or_else(|err| error!("increment task list version in redis error: {:}", err));
+}
+
+#[async_trait]
 impl TaskRepositoryInterface for TaskRepository {
-    fn insert(&mut self, new_task: Task) -> Result<Task, Box<dyn Error>> {
-        match insert_into(task)
-            .values(&new_task)
-            .execute(&mut self.db_connection)
-        {
-            Ok(_) => Ok(new_task),
-            Err(err) => Err(Box::new(err)),
+    async fn insert(
+        &self,
+        new_task: Task,
+        tags: Vec<String>,
+        idempotency_key: Option<IdempotencyKey>,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError> {
+        // the task is created for the user, a shared project can hand it to its owner
+        let user_id = new_task.owner.clone();
+        let (result, idempotency_key, assignment) =
+            postgres::run(&self.db_pool, move |connection| {
+                connection.transaction(|connection| {
+                    let result = apply_insert(connection, new_task, &tags, &user_id, &context)?;
+                    let assignment = new_assignment(&result.task, &context);
+
+                    // recorded with the task, so of two concurrent requests with
+                    // the same key only one creates it
+                    let idempotency_key = match idempotency_key {
+                        Some(mut idempotency_key) => {
+                            idempotency_key.response = serde_json::to_value(&result)?;
+                            insert_idempotency_key(connection, &idempotency_key)?;
+                            Some(idempotency_key)
+                        }
+                        None => None,
+                    };
+
+                    Ok((result, idempotency_key, assignment))
+                })
+            })
+            .await?;
+
+        self.invalidate(&result.task.owner, None).await;
+        if let Some(assignment) = assignment {
+            self.events.publish_assignment(assignment);
+        }
+
+        if let Some(idempotency_key) = idempotency_key {
+            let key = idempotency_cache_key(&idempotency_key.owner, &idempotency_key.key);
+            let ttl = (idempotency_key.expires_at - Utc::now())
+                .num_seconds()
+                .max(1);
+            self.redis_client
+                .set_with_ttl(key, &idempotency_key, ttl as u64)
+                .await
+                .unwrap_or_else(|err| error!("set idempotency key to redis error: {:}", err));
         }
+
+        Ok(result)
     }
 
-    fn find_all(&mut self, user_id: String) -> Result<Vec<Task>, Box<dyn Error>> {
-        let key = format!("task::{}", user_id);
+    async fn bulk(
+        &self,
+        user_id: String,
+        writes: Vec<TaskWrite>,
+        atomic: bool,
+        context: AuditContext,
+    ) -> Result<Vec<TaskWriteResult>, AppError> {
+        let (results, changes) = postgres::run(&self.db_pool, move |connection| {
+            let mut results = Vec::with_capacity(writes.len());
+            // the tasks of a batch can belong to several owners
+            let mut changed_ids: HashMap<String, Vec<Uuid>> = HashMap::new();
+            let mut assignments = Vec::new();
+            let mut aborted = false;
+
+            let outcome = connection.transaction(|connection| {
+                for write in writes {
+                    // every write has its own savepoint, so a failed one is
+                    // undone without the writes before it
+                    let result = connection.transaction(|connection| {
+                        apply_write(connection, write, &user_id, &context)
+                    });
+                    match result {
+                        Ok((detail, task_owner, ids, assignment)) => {
+                            changed_ids.entry(task_owner).or_default().extend(ids);
+                            assignments.extend(assignment);
+                            results.push(Ok(detail));
+                        }
+                        Err(err) => {
+                            results.push(Err(err));
+                            // in atomic mode the writes after it are never tried
+                            if atomic {
+                                aborted = true;
+                                return Err(AppError::from(DieselError::RollbackTransaction));
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            });
 
-        match self.redis_client.get::<Vec<Task>>(key.clone()) {
-            Ok(result) => return Ok(result),
-            Err(err) => error!("get all task from redis error: {:}", err),
+            match outcome {
+                Ok(()) => Ok((results, Some((changed_ids, assignments)))),
+                // the batch was rolled back, there is nothing to invalidate
+                Err(_) if aborted => Ok((results, None)),
+                Err(err) => Err(err),
+            }
+        })
+        .await?;
+
+        // once for the whole batch
+        if let Some((changed_ids, assignments)) = changes {
+            for (task_owner, ids) in changed_ids {
+                invalidate_tasks(&self.redis_client, &task_owner, &ids).await;
+            }
+            for assignment in assignments {
+                self.events.publish_assignment(assignment);
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn find_idempotency_key(
+        &self,
+        user_id: String,
+        key: String,
+    ) -> Result<Option<IdempotencyKey>, AppError> {
+        // redis only holds keys that have not expired, postgres has them all
+        let cache_key = idempotency_cache_key(&user_id, &key);
+        match self.redis_client.get::<IdempotencyKey>(cache_key).await {
+            Ok(result) => return Ok(Some(result)),
+            Err(err) => error!("get idempotency key from redis error: {:}", err),
+        }
+
+        postgres::run(&self.db_pool, move |connection| {
+            let result = schema::idempotency_key::table
+                .filter(schema::idempotency_key::owner.eq(&user_id))
+                .filter(schema::idempotency_key::key.eq(&key))
+                .filter(schema::idempotency_key::expires_at.gt(Utc::now()))
+                .select(IdempotencyKey::as_select())
+                .first(connection)
+                .optional()?;
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn find_all(&self, user_id: String, filter: TaskFilter) -> Result<TaskPage, AppError> {
+        // tasks shared with the user are written without bumping the user's
+        // list version, so their lists are not cached
+        let shared_user = user_id.clone();
+        let shared = postgres::run(&self.db_pool, move |connection| {
+            let shared = select(exists(
+                schema::share::table.filter(schema::share::user_id.eq(shared_user)),
+            ))
+            .get_result::<bool>(connection)?;
+            Ok(shared)
+        })
+        .await?;
+
+        // overdue results change as time passes, not only on writes, and a
+        // page without a version cannot be tied to a generation, so neither is cached
+        let key = if filter.overdue || shared {
+            None
+        } else {
+            match self
+                .redis_client
+                .get_counter(list_version_key(&user_id))
+                .await
+            {
+                Ok(list_version) => Some(list_key(&user_id, list_version, &filter)?),
+                Err(err) => {
+                    error!("get task list version from redis error: {:}", err);
+                    None
+                }
+            }
         };
 
-        let db_result = match task
-            .filter(owner.eq(user_id.clone()))
-            .limit(10)
-            .select(Task::as_select())
-            .load(&mut self.db_connection)
+        if let Some(key) = &key {
+            match self.redis_client.get::<TaskPage>(key.clone()).await {
+                Ok(result) => return Ok(result),
+                Err(err) => error!("get all task from redis error: {:}", err),
+            };
+        }
+
+        let db_result = postgres::run(&self.db_pool, move |connection| {
+            let total: i64 = filtered_tasks(&user_id, &filter)
+                .count()
+                .get_result(connection)?;
+
+            let query = filtered_tasks(&user_id, &filter);
+            let query = match (filter.sort, filter.order) {
+                (TaskSort::Id, SortOrder::Asc) => query.order(id.asc()),
+                (TaskSort::Id, SortOrder::Desc) => query.order(id.desc()),
+                (TaskSort::Title, SortOrder::Asc) => query.order((title.asc(), id.asc())),
+                (TaskSort::Title, SortOrder::Desc) => query.order((title.desc(), id.asc())),
+                (TaskSort::CreatedAt, SortOrder::Asc) => query.order((created_at.asc(), id.asc())),
+                (TaskSort::CreatedAt, SortOrder::Desc) => {
+                    query.order((created_at.desc(), id.asc()))
+                }
+                (TaskSort::UpdatedAt, SortOrder::Asc) => query.order((updated_at.asc(), id.asc())),
+                (TaskSort::UpdatedAt, SortOrder::Desc) => {
+                    query.order((updated_at.desc(), id.asc()))
+                }
+                (TaskSort::CompletedAt, SortOrder::Asc) => {
+                    query.order((completed_at.asc().nulls_last(), id.asc()))
+                }
+                (TaskSort::CompletedAt, SortOrder::Desc) => {
+                    query.order((completed_at.desc().nulls_last(), id.asc()))
+                }
+                (TaskSort::DueAt, SortOrder::Asc) => {
+                    query.order((due_at.asc().nulls_last(), id.asc()))
+                }
+                (TaskSort::DueAt, SortOrder::Desc) => {
+                    query.order((due_at.desc().nulls_last(), id.asc()))
+                }
+                (TaskSort::Priority, SortOrder::Asc) => query.order((priority.asc(), id.asc())),
+                (TaskSort::Priority, SortOrder::Desc) => query.order((priority.desc(), id.asc())),
+            };
+
+            let tasks: Vec<Task> = query
+                .offset(filter.offset)
+                .limit(filter.limit)
+                .select(Task::as_select())
+                .load(connection)?;
+            let tasks = with_details(connection, &user_id, tasks)?;
+
+            Ok(TaskPage {
+                next_cursor: next_cursor(filter.offset, tasks.len(), total),
+                tasks,
+                total,
+            })
+        })
+        .await?;
+
+        if let Some(key) = key {
+            self.redis_client
+                .set(key, &db_result)
+                .await
+                .unwrap_or_else(|err| error!("set all task to redis error: {:}", err));
+        }
+
+        Ok(db_result)
+    }
+
+    async fn search(
+        &self,
+        user_id: String,
+        search: TaskSearch,
+    ) -> Result<TaskSearchPage, AppError> {
+        // the terms are plain words, so joining them cannot inject tsquery operators
+        let terms = search
+            .terms
+            .iter()
+            .map(|term| format!("{}:*", term))
+            .collect::<Vec<String>>()
+            .join(" & ");
+
+        postgres::run(&self.db_pool, move |connection| {
+            let SearchCount { total } = sql_query(format!(
+                "SELECT count(*) AS total \
+                 FROM task, to_tsquery('english', $2) AS query \
+                 WHERE {} AND deleted_at IS NULL AND search_vector @@ query \
+                 AND ($3::BOOLEAN IS NULL OR completed = $3)",
+                accessible_sql(1)
+            ))
+            .bind::<sql_types::Text, _>(&user_id)
+            .bind::<sql_types::Text, _>(&terms)
+            .bind::<sql_types::Nullable<sql_types::Bool>, _>(search.completed)
+            .get_result(connection)?;
+
+            let rows: Vec<SearchRow> = sql_query(format!(
+                "SELECT task.*, ts_rank(search_vector, query) AS rank, \
+                 ts_headline('english', title, query, \
+                     'StartSel=<mark>, StopSel=</mark>, HighlightAll=true') AS title_highlight, \
+                 ts_headline('english', description, query, \
+                     'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS description_highlight \
+                 FROM task, to_tsquery('english', $2) AS query \
+                 WHERE {} AND deleted_at IS NULL AND search_vector @@ query \
+                 AND ($3::BOOLEAN IS NULL OR completed = $3) \
+                 ORDER BY rank DESC, id \
+                 OFFSET $4 LIMIT $5",
+                accessible_sql(1)
+            ))
+            .bind::<sql_types::Text, _>(&user_id)
+            .bind::<sql_types::Text, _>(&terms)
+            .bind::<sql_types::Nullable<sql_types::Bool>, _>(search.completed)
+            .bind::<sql_types::BigInt, _>(search.offset)
+            .bind::<sql_types::BigInt, _>(search.limit)
+            .load(connection)?;
+
+            let mut highlights = Vec::with_capacity(rows.len());
+            let mut tasks = Vec::with_capacity(rows.len());
+            for row in rows {
+                highlights.push((
+                    row.rank,
+                    TaskHighlight {
+                        title: row.title_highlight,
+                        description: row.description_highlight,
+                    },
+                ));
+                tasks.push(row.task_row);
+            }
+
+            let results: Vec<TaskSearchResult> = with_details(connection, &user_id, tasks)?
+                .into_iter()
+                .zip(highlights)
+                .map(|(detail, (rank, highlight))| TaskSearchResult {
+                    task: detail,
+                    rank,
+                    highlight,
+                })
+                .collect();
+
+            Ok(TaskSearchPage {
+                next_cursor: next_cursor(search.offset, results.len(), total),
+                results,
+                total,
+            })
+        })
+        .await
+    }
+
+    async fn find_by_id(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<Option<TaskDetail>, AppError> {
+        // a task without a version cannot be tied to a generation, so it is not cached
+        let key = match self
+            .redis_client
+            .get_counter(task_version_key(&user_id, task_id))
+            .await
         {
-            Ok(result) => result,
-            Err(err) => return Err(Box::new(err)),
+            Ok(task_version) => Some(task_key(&user_id, task_id, task_version)),
+            Err(err) => {
+                error!("get task version from redis error: {:}", err);
+                None
+            }
         };
 
-        if !db_result.is_empty() {
-            match self.redis_client.set(key, &db_result) {
-                Ok(_) => return Ok(db_result),
-                Err(err) => error!("set all task to redis error: {:}", err),
+        if let Some(key) = &key {
+            match self.redis_client.get::<TaskDetail>(key.clone()).await {
+                Ok(result) => return Ok(Some(result)),
+                Err(err) => error!("get task from redis error: {:}", err),
             }
         }
 
-        Ok(db_result)
+        let reader = user_id.clone();
+        let result = postgres::run(&self.db_pool, move |connection| {
+            let result = task
+                .filter(id.eq(task_id))
+                .filter(accessible(&reader, ShareRole::Viewer))
+                .filter(deleted_at.is_null())
+                .select(Task::as_select())
+                .first(connection)
+                .optional()?;
+
+            match result {
+                Some(result) => Ok(with_details(connection, &reader, vec![result])?.pop()),
+                None => Ok(None),
+            }
+        })
+        .await?;
+
+        // tasks are only cached for their owner, whose writes invalidate them
+        if let (Some(key), Some(result)) = (
+            key,
+            result
+                .as_ref()
+                .filter(|result| result.task.owner == user_id),
+        ) {
+            self.redis_client
+                .set(key, result)
+                .await
+                .unwrap_or_else(|err| error!("set tasks to redis error: {:}", err));
+        }
+
+        Ok(result)
+    }
+
+    async fn find_lineage(&self, task_id: Uuid, user_id: String) -> Result<Vec<Uuid>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let lineage: Vec<TaskId> = sql_query(format!(
+                "WITH RECURSIVE lineage AS ( \
+                     SELECT id, parent_id, 0 AS depth FROM task \
+                     WHERE id = $1 AND {} AND deleted_at IS NULL \
+                     UNION ALL \
+                     SELECT task.id, task.parent_id, lineage.depth + 1 \
+                     FROM task JOIN lineage ON task.id = lineage.parent_id \
+                     WHERE lineage.depth < $3 \
+                 ) \
+                 SELECT id AS task_id FROM lineage ORDER BY depth",
+                accessible_sql(2)
+            ))
+            .bind::<sql_types::Uuid, _>(task_id)
+            .bind::<sql_types::Text, _>(user_id)
+            .bind::<sql_types::Integer, _>(MAX_TREE_DEPTH)
+            .load(connection)?;
+
+            Ok(lineage.into_iter().map(|row| row.task_id).collect())
+        })
+        .await
     }
 
-    fn find_by_id(
-        &mut self,
-        task_id: String,
+    async fn find_tree(&self, task_id: Uuid, user_id: String) -> Result<Vec<TaskDetail>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            // subtasks the user cannot access are left out with everything below them
+            let tasks: Vec<Task> = sql_query(format!(
+                "WITH RECURSIVE tree AS ( \
+                     SELECT task.*, 0 AS depth FROM task \
+                     WHERE id = $1 AND {0} AND deleted_at IS NULL \
+                     UNION ALL \
+                     SELECT task.*, tree.depth + 1 \
+                     FROM task JOIN tree ON task.parent_id = tree.id \
+                     WHERE {0} AND task.deleted_at IS NULL AND tree.depth < $3 \
+                 ) \
+                 SELECT * FROM tree ORDER BY depth, created_at, id",
+                accessible_sql(2)
+            ))
+            .bind::<sql_types::Uuid, _>(task_id)
+            .bind::<sql_types::Text, _>(&user_id)
+            .bind::<sql_types::Integer, _>(MAX_TREE_DEPTH)
+            .load(connection)?;
+
+            with_details(connection, &user_id, tasks)
+        })
+        .await
+    }
+
+    async fn find_by_ids(
+        &self,
+        task_ids: Vec<Uuid>,
         user_id: String,
-    ) -> Result<Option<Task>, Box<dyn Error>> {
-        let key = format!("task::{}::{}", user_id, task_id);
+    ) -> Result<Vec<TaskDetail>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let tasks = task
+                .filter(id.eq_any(&task_ids))
+                .filter(accessible(&user_id, ShareRole::Viewer))
+                .filter(deleted_at.is_null())
+                .order(id.asc())
+                .select(Task::as_select())
+                .load(connection)?;
 
-        match self.redis_client.get::<Task>(key.clone()) {
-            Ok(result) => return Ok(Some(result)),
-            Err(err) => error!("get task from redis error: {:}", err),
+            with_details(connection, &user_id, tasks)
+        })
+        .await
+    }
+
+    async fn find_dependencies(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        include_trashed: bool,
+    ) -> Result<Vec<TaskDependency>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            // UNION drops edges already walked, so the walk ends even on a cycle
+            let dependencies = sql_query(format!(
+                "WITH RECURSIVE graph AS ( \
+                     SELECT task_dependency.* FROM task_dependency \
+                     JOIN task ON task.id = task_dependency.task_id \
+                     JOIN task blocker ON blocker.id = task_dependency.blocker_id \
+                     WHERE task_dependency.task_id = $1 AND {} \
+                     AND ($3 OR blocker.deleted_at IS NULL) \
+                     UNION \
+                     SELECT task_dependency.* FROM task_dependency \
+                     JOIN graph ON task_dependency.task_id = graph.blocker_id \
+                     JOIN task blocker ON blocker.id = task_dependency.blocker_id \
+                     WHERE $3 OR blocker.deleted_at IS NULL \
+                 ) \
+                 SELECT * FROM graph ORDER BY task_id, blocker_id",
+                accessible_sql(2)
+            ))
+            .bind::<sql_types::Uuid, _>(task_id)
+            .bind::<sql_types::Text, _>(user_id)
+            .bind::<sql_types::Bool, _>(include_trashed)
+            .load(connection)?;
+
+            Ok(dependencies)
+        })
+        .await
+    }
+
+    async fn find_open_blockers(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+    ) -> Result<Vec<Uuid>, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let found = task
+                .filter(id.eq(task_id))
+                .filter(accessible(&user_id, ShareRole::Viewer))
+                .filter(deleted_at.is_null())
+                .select(id)
+                .first::<Uuid>(connection)
+                .optional()?;
+
+            match found {
+                Some(task_id) => find_open_blocker_ids(connection, task_id),
+                None => Ok(Vec::new()),
+            }
+        })
+        .await
+    }
+
+    async fn add_dependency(
+        &self,
+        task_id: Uuid,
+        blocker_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError> {
+        let result = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let dependent = task
+                    .filter(id.eq(task_id))
+                    .filter(accessible(&user_id, ShareRole::Viewer))
+                    .filter(deleted_at.is_null())
+                    .select(Task::as_select())
+                    .for_share()
+                    .first(connection)
+                    .optional()?
+                    .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+                require_role(connection, &user_id, &dependent, ShareRole::Editor)?;
+                let before = load_states(connection, &[task_id])?;
+
+                // both tasks belong to the same owner, and the user must see the blocker
+                task.filter(id.eq(blocker_id))
+                    .filter(owner.eq(&dependent.owner))
+                    .filter(accessible(&user_id, ShareRole::Viewer))
+                    .filter(deleted_at.is_null())
+                    .select(id)
+                    .for_share()
+                    .first::<Uuid>(connection)
+                    .optional()?
+                    .ok_or_else(|| {
+                        AppError::Validation(format!("blocker_id {} does not exist", blocker_id))
+                    })?;
+
+                // adding an existing blocker again changes nothing
+                let inserted = insert_into(schema::task_dependency::table)
+                    .values(&TaskDependency {
+                        task_id,
+                        blocker_id,
+                        created_at: Utc::now(),
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(connection)?;
+                let dependent = if inserted > 0 {
+                    bump_version(connection, task_id)?
+                } else {
+                    dependent
+                };
+                record_changes(
+                    connection,
+                    &[task_id],
+                    &before,
+                    TaskAction::Updated,
+                    &context,
+                )?;
+
+                Ok(with_details(connection, &user_id, vec![dependent])?
+                    .pop()
+                    .unwrap_or_default())
+            })
+        })
+        .await?;
+
+        self.invalidate(&result.task.owner, Some(result.task.id))
+            .await;
+
+        Ok(result)
+    }
+
+    async fn remove_dependency(
+        &self,
+        task_id: Uuid,
+        blocker_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError> {
+        let result = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let dependent = task
+                    .filter(id.eq(task_id))
+                    .filter(accessible(&user_id, ShareRole::Viewer))
+                    .filter(deleted_at.is_null())
+                    .select(Task::as_select())
+                    .first(connection)
+                    .optional()?
+                    .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+                require_role(connection, &user_id, &dependent, ShareRole::Editor)?;
+                let before = load_states(connection, &[task_id])?;
+
+                let affected = delete(schema::task_dependency::table)
+                    .filter(schema::task_dependency::task_id.eq(task_id))
+                    .filter(schema::task_dependency::blocker_id.eq(blocker_id))
+                    .execute(connection)?;
+
+                if affected == 0 {
+                    return Err(AppError::NotFound(format!(
+                        "task {} is not blocked by {}",
+                        task_id, blocker_id
+                    )));
+                }
+                let dependent = bump_version(connection, task_id)?;
+                record_changes(
+                    connection,
+                    &[task_id],
+                    &before,
+                    TaskAction::Updated,
+                    &context,
+                )?;
+
+                Ok(with_details(connection, &user_id, vec![dependent])?
+                    .pop()
+                    .unwrap_or_default())
+            })
+        })
+        .await?;
+
+        self.invalidate(&result.task.owner, Some(result.task.id))
+            .await;
+
+        Ok(result)
+    }
+
+    async fn update(
+        &self,
+        update_task: Task,
+        user_id: String,
+        tags: Option<Vec<String>>,
+        next_occurrence: Option<Task>,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError> {
+        let (result, changed_ids, assignment) = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                apply_update(
+                    connection,
+                    update_task,
+                    tags,
+                    next_occurrence,
+                    &user_id,
+                    &context,
+                )
+            })
+        })
+        .await?;
+
+        // invalidate after the commit, see invalidate_tasks
+        invalidate_tasks(&self.redis_client, &result.task.owner, &changed_ids).await;
+        if let Some(assignment) = assignment {
+            self.events.publish_assignment(assignment);
         }
 
-        let result = task
-            .filter(id.eq(task_id))
-            .filter(owner.eq(user_id))
-            .select(Task::as_select())
-            .first(&mut self.db_connection)?;
+        Ok(result)
+    }
+
+    async fn delete(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        mode: SubtaskDeleteMode,
+        expected_versions: Option<Vec<i32>>,
+        context: AuditContext,
+    ) -> Result<(), AppError> {
+        let (task_owner, changed_ids) = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                apply_delete(
+                    connection,
+                    task_id,
+                    &user_id,
+                    mode,
+                    expected_versions,
+                    &context,
+                )
+            })
+        })
+        .await?;
 
-        self.redis_client
-            .set(key, &result)
-            .unwrap_or_else(|err| error!("set tasks to redis error: {:}", err));
+        invalidate_tasks(&self.redis_client, &task_owner, &changed_ids).await;
 
-        Ok(Some(result))
+        Ok(())
     }
 
-    fn update(&mut self, update_task: Task) -> Result<Task, Box<dyn Error>> {
-        let task_id = &update_task.id;
-        let user_id = &update_task.owner;
+    async fn find_trash(
+        &self,
+        user_id: String,
+        offset: i64,
+        limit: i64,
+    ) -> Result<TaskPage, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            let trash = task
+                .filter(owner.eq(&user_id))
+                .filter(deleted_at.is_not_null());
 
-        let key = format!("task::{}::{}", user_id, task_id);
+            let total: i64 = trash.count().get_result(connection)?;
 
-        self.redis_client.delete(key).unwrap_or_else(|err| {
-            error!("delete task in redis error: {:}", err);
-        });
+            let tasks: Vec<Task> = trash
+                .order((deleted_at.desc(), id.asc()))
+                .offset(offset)
+                .limit(limit)
+                .select(Task::as_select())
+                .load(connection)?;
+            let tasks = with_details(connection, &user_id, tasks)?;
 
-        match update(task)
-            .filter(id.eq(task_id))
-            .set(&update_task)
-            .execute(&mut self.db_connection)
-        {
-            Ok(_) => Ok(update_task),
-            Err(err) => Err(Box::new(err)),
+            Ok(TaskPage {
+                next_cursor: next_cursor(offset, tasks.len(), total),
+                tasks,
+                total,
+            })
+        })
+        .await
+    }
+
+    async fn restore(
+        &self,
+        task_id: Uuid,
+        user_id: String,
+        context: AuditContext,
+    ) -> Result<TaskDetail, AppError> {
+        let (result, changed_ids) = postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let trashed = task
+                    .filter(id.eq(task_id))
+                    .filter(accessible(&user_id, ShareRole::Viewer))
+                    .filter(deleted_at.is_not_null())
+                    .select(Task::as_select())
+                    .for_update()
+                    .first(connection)
+                    .optional()?
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("task {} is not in the trash", task_id))
+                    })?;
+                require_role(connection, &user_id, &trashed, ShareRole::Owner)?;
+
+                // a task restored below a trashed parent would stay hidden
+                if let Some(parent) = trashed.parent_id {
+                    let parent_deleted_at = task
+                        .filter(id.eq(parent))
+                        .select(deleted_at)
+                        .for_share()
+                        .first::<Option<DateTime<Utc>>>(connection)?;
+                    if parent_deleted_at.is_some() {
+                        return Err(AppError::Conflict(format!(
+                            "parent task {} is in the trash, restore it first",
+                            parent
+                        )));
+                    }
+                }
+
+                // subtasks trashed on their own before the task stay in the trash
+                let mut changed_ids = find_tree_ids(connection, &[task_id], trashed.deleted_at)?;
+                changed_ids.push(task_id);
+                let before = load_states(connection, &changed_ids)?;
+
+                update(task)
+                    .filter(id.eq_any(&changed_ids))
+                    .set((
+                        deleted_at.eq(None::<DateTime<Utc>>),
+                        updated_at.eq(Utc::now()),
+                        version.eq(version + 1),
+                    ))
+                    .execute(connection)?;
+                record_changes(
+                    connection,
+                    &changed_ids,
+                    &before,
+                    TaskAction::Restored,
+                    &context,
+                )?;
+
+                // restored blockers block their dependents again
+                let dependent_ids = find_dependent_ids(connection, &changed_ids)?;
+                changed_ids.extend(dependent_ids);
+
+                let restored = task
+                    .filter(id.eq(task_id))
+                    .select(Task::as_select())
+                    .first(connection)?;
+                let result = with_details(connection, &user_id, vec![restored])?
+                    .pop()
+                    .unwrap_or_default();
+
+                Ok((result, changed_ids))
+            })
+        })
+        .await?;
+
+        invalidate_tasks(&self.redis_client, &result.task.owner, &changed_ids).await;
+
+        Ok(result)
+    }
+
+    async fn purge(&self, trashed_before: DateTime<Utc>) -> Result<usize, AppError> {
+        // tasks leave every cached list when trashed, so nothing is invalidated,
+        // and their tags and dependencies go with them through the foreign keys
+        postgres::run(&self.db_pool, move |connection| {
+            connection.transaction(|connection| {
+                let purged: Vec<(Uuid, String)> = delete(task)
+                    .filter(deleted_at.lt(trashed_before))
+                    .returning((id, owner))
+                    .get_results(connection)?;
+
+                let context = AuditContext::system();
+                let now = Utc::now();
+                let events: Vec<TaskEvent> = purged
+                    .into_iter()
+                    .map(|(task_id, task_owner)| TaskEvent {
+                        id: Uuid::new_v4(),
+                        task_id,
+                        owner: task_owner,
+                        actor: context.actor.clone(),
+                        action: TaskAction::Purged,
+                        ref_id: context.ref_id.clone(),
+                        changes: serde_json::Value::Object(serde_json::Map::new()),
+                        created_at: now,
+                    })
+                    .collect();
+                insert_events(connection, &events)?;
+
+                Ok(events.len())
+            })
+        })
+        .await
+    }
+
+    async fn find_events(
+        &self,
+        user_id: String,
+        task_id: Option<Uuid>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<TaskEventPage, AppError> {
+        postgres::run(&self.db_pool, move |connection| {
+            // the history of a task is also read by the users it is shared with,
+            // while a purged task only has its history left for its owner
+            let shared = match task_id {
+                Some(task_id) => task
+                    .filter(id.eq(task_id))
+                    .filter(accessible(&user_id, ShareRole::Viewer))
+                    .select(id)
+                    .first::<Uuid>(connection)
+                    .optional()?
+                    .is_some(),
+                None => false,
+            };
+
+            let events_query = || {
+                let mut query = schema::task_event::table.into_boxed();
+                if !shared {
+                    query = query.filter(schema::task_event::owner.eq(&user_id));
+                }
+                if let Some(task_id) = task_id {
+                    query = query.filter(schema::task_event::task_id.eq(task_id));
+                }
+                query
+            };
+
+            let total: i64 = events_query().count().get_result(connection)?;
+
+            let events: Vec<TaskEvent> = events_query()
+                .order((
+                    schema::task_event::created_at.desc(),
+                    schema::task_event::id.asc(),
+                ))
+                .offset(offset)
+                .limit(limit)
+                .select(TaskEvent::as_select())
+                .load(connection)?;
+
+            Ok(TaskEventPage {
+                next_cursor: next_cursor(offset, events.len(), total),
+                events,
+                total,
+            })
+        })
+        .await
+    }
+}
+
+// caps the recursive queries, the service keeps trees far shallower and
+// acyclic, so this only guards against rows written around it
+const MAX_TREE_DEPTH: i32 = 64;
+
+#[derive(QueryableByName)]
+struct TaskId {
+    #[diesel(sql_type = sql_types::Uuid)]
+    task_id: Uuid,
+}
+
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[diesel(embed)]
+    task_row: Task,
+    #[diesel(sql_type = sql_types::Float)]
+    rank: f32,
+    #[diesel(sql_type = sql_types::Text)]
+    title_highlight: String,
+    #[diesel(sql_type = sql_types::Text)]
+    description_highlight: String,
+}
+
+#[derive(QueryableByName)]
+struct SearchCount {
+    #[diesel(sql_type = sql_types::BigInt)]
+    total: i64,
+}
+
+fn task_key(user_id: &str, task_id: Uuid, task_version: i64) -> String {
+    format!("task::{}::{}::{}", user_id, task_id, task_version)
+}
+
+fn task_version_key(user_id: &str, task_id: Uuid) -> String {
+    format!("task::{}::{}::version", user_id, task_id)
+}
+
+fn idempotency_cache_key(user_id: &str, key: &str) -> String {
+    format!("idempotency::{}::{}", user_id, key)
+}
+
+fn list_version_key(user_id: &str) -> String {
+    format!("task::{}::list::version", user_id)
+}
+
+fn list_key(user_id: &str, list_version: i64, filter: &TaskFilter) -> Result<String, AppError> {
+    // every query shape gets its own key so pages and filters never collide
+    Ok(format!(
+        "task::{}::list::{}::{}",
+        user_id,
+        list_version,
+        serde_json::to_string(filter)?
+    ))
+}
+
+// the cursor of the page after the one read, None on the last page
+pub fn next_cursor(offset: i64, count: usize, total: i64) -> Option<String> {
+    let next_offset = offset + count as i64;
+    if next_offset < total {
+        Some(next_offset.to_string())
+    } else {
+        None
+    }
+}
+
+fn filtered_tasks<'a>(
+    user_id: &'a str,
+    filter: &'a TaskFilter,
+) -> schema::task::BoxedQuery<'a, Pg> {
+    let mut query = task
+        .filter(accessible(user_id, ShareRole::Viewer))
+        .filter(deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(is_completed) = filter.completed {
+        query = query.filter(completed.eq(is_completed));
+    }
+
+    if let Some(after) = filter.due_after {
+        query = query.filter(due_at.ge(after));
+    }
+
+    if let Some(before) = filter.due_before {
+        query = query.filter(due_at.lt(before));
+    }
+
+    match filter.project {
+        Some(ProjectScope::Inbox) => query = query.filter(project_id.is_null()),
+        Some(ProjectScope::Project(project)) => query = query.filter(project_id.eq(project)),
+        None => {}
+    }
+
+    if let Some(parent) = filter.parent_id {
+        query = query.filter(parent_id.eq(parent));
+    }
+
+    match &filter.assignee {
+        Some(AssigneeScope::Unassigned) => query = query.filter(assignee_id.is_null()),
+        Some(AssigneeScope::User(assignee)) => query = query.filter(assignee_id.eq(assignee)),
+        None => {}
+    }
+
+    if let Some(title_filter) = &filter.title {
+        // match the title as a literal substring
+        let escaped = title_filter
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query = query.filter(title.ilike(format!("%{}%", escaped)));
+    }
+
+    // shared tasks carry the tags of their owner, so tags match by name alone
+    if !filter.tags.is_empty() {
+        let tagged = schema::task_tag::table
+            .inner_join(schema::tag::table)
+            .filter(schema::tag::name.eq_any(&filter.tags))
+            .select(schema::task_tag::task_id);
+
+        query = match filter.tag_match {
+            TagMatch::Any => query.filter(id.eq_any(tagged)),
+            // tag names are unique per owner, so a task carrying every tag
+            // joins exactly once per requested name
+            TagMatch::All => query.filter(
+                id.eq_any(
+                    tagged
+                        .group_by(schema::task_tag::task_id)
+                        .having(count_star().eq(filter.tags.len() as i64)),
+                ),
+            ),
+        };
+    }
+
+    query
+}
+
+// tasks the user owns or was granted at least the given role on, either on
+// the task itself or on its project
+fn accessible<'a>(
+    user_id: &'a str,
+    role: ShareRole,
+) -> Box<
+    dyn BoxableExpression<schema::task::table, Pg, SqlType = sql_types::Nullable<sql_types::Bool>>
+        + 'a,
+> {
+    let roles = role.and_above();
+    let shares = move || {
+        schema::share::table
+            .filter(schema::share::user_id.eq(user_id))
+            .filter(schema::share::role.eq_any(roles.clone()))
+    };
+
+    Box::new(
+        owner
+            .eq(user_id)
+            .nullable()
+            .or(id
+                .nullable()
+                .eq_any(shares().select(schema::share::task_id)))
+            .or(project_id.eq_any(shares().select(schema::share::project_id))),
+    )
+}
+
+// accessible for any role in raw SQL, with the user bound to the given parameter
+fn accessible_sql(user_param: usize) -> String {
+    format!(
+        "(task.owner = ${0} OR EXISTS ( \
+             SELECT 1 FROM share WHERE share.user_id = ${0} \
+             AND (share.task_id = task.id OR share.project_id = task.project_id) \
+         ))",
+        user_param
+    )
+}
+
+// the role of the user on each of the given tasks they can access, the
+// highest one when they were granted roles on both the task and its project
+pub fn load_roles(
+    connection: &mut PgConnection,
+    user_id: &str,
+    tasks: &[Task],
+) -> Result<HashMap<Uuid, ShareRole>, AppError> {
+    let mut roles = HashMap::new();
+    let mut shared_ids = Vec::new();
+    let mut project_ids = Vec::new();
+    for task_row in tasks {
+        if task_row.owner == user_id {
+            roles.insert(task_row.id, ShareRole::Owner);
+        } else {
+            shared_ids.push(task_row.id);
+            project_ids.extend(task_row.project_id);
         }
     }
+    if shared_ids.is_empty() {
+        return Ok(roles);
+    }
 
-    fn delete(&mut self, task_id: String, user_id: String) -> Result<(), Box<dyn Error>> {
-        let key = format!("task::{}::{}", user_id, task_id);
+    let grants: Vec<(Option<Uuid>, Option<Uuid>, ShareRole)> = schema::share::table
+        .filter(schema::share::user_id.eq(user_id))
+        .filter(
+            schema::share::task_id
+                .eq_any(&shared_ids)
+                .or(schema::share::project_id.eq_any(&project_ids)),
+        )
+        .select((
+            schema::share::task_id,
+            schema::share::project_id,
+            schema::share::role,
+        ))
+        .load(connection)?;
 
-        self.redis_client.delete(key).unwrap_or_else(|err| {
-            error!("delete task in redis error: {:}", err);
-        });
+    for task_row in tasks.iter().filter(|task_row| task_row.owner != user_id) {
+        let granted = grants
+            .iter()
+            .filter(|(task_id, project, _)| {
+                *task_id == Some(task_row.id)
+                    || (project.is_some() && *project == task_row.project_id)
+            })
+            .map(|(_, _, role)| *role)
+            .max();
+        if let Some(role) = granted {
+            roles.insert(task_row.id, role);
+        }
+    }
 
-        match delete(task)
-            .filter(id.eq(task_id))
-            .filter(owner.eq(user_id))
-            .execute(&mut self.db_connection)
+    Ok(roles)
+}
+
+// a task the user cannot access at all is not found, one they can access
+// with a lower role is forbidden
+pub fn require_role(
+    connection: &mut PgConnection,
+    user_id: &str,
+    task_row: &Task,
+    required: ShareRole,
+) -> Result<ShareRole, AppError> {
+    let role = load_roles(connection, user_id, slice::from_ref(task_row))?
+        .remove(&task_row.id)
+        .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_row.id)))?;
+    if role < required {
+        return Err(AppError::Forbidden(format!(
+            "task {} is shared with you as {}",
+            task_row.id,
+            role.as_str()
+        )));
+    }
+
+    Ok(role)
+}
+
+// the foreign key only proves the project exists, the user must also own it
+// or be an editor of it, and the share lock keeps it from being deleted until
+// the task is written. Returns the owner of the project, who owns its tasks
+fn lock_project(
+    connection: &mut PgConnection,
+    project: Uuid,
+    user_id: &str,
+) -> Result<String, AppError> {
+    let editors = schema::share::table
+        .filter(schema::share::user_id.eq(user_id))
+        .filter(schema::share::role.eq_any(ShareRole::Editor.and_above()))
+        .select(schema::share::project_id);
+
+    let project_owner = schema::project::table
+        .filter(schema::project::id.eq(project))
+        .filter(
+            schema::project::owner
+                .eq(user_id)
+                .or(schema::project::id.nullable().eq_any(editors)),
+        )
+        .select(schema::project::owner)
+        .for_share()
+        .first::<String>(connection)
+        .optional()?
+        .ok_or_else(|| AppError::Validation(format!("project_id {} does not exist", project)))?;
+
+    Ok(project_owner)
+}
+
+// an assignee has to be able to access the task, as its owner or through a share
+fn check_assignee(connection: &mut PgConnection, task_row: &Task) -> Result<(), AppError> {
+    let Some(assignee) = &task_row.assignee_id else {
+        return Ok(());
+    };
+
+    if load_roles(connection, assignee, slice::from_ref(task_row))?.is_empty() {
+        return Err(AppError::Validation(format!(
+            "assignee_id {} cannot access task {}",
+            assignee, task_row.id
+        )));
+    }
+
+    Ok(())
+}
+
+fn assigned(
+    task_row: &Task,
+    previous_assignee_id: Option<String>,
+    context: &AuditContext,
+) -> TaskAssigned {
+    TaskAssigned {
+        task_id: task_row.id,
+        owner: task_row.owner.clone(),
+        actor: context.actor.clone(),
+        previous_assignee_id,
+        assignee_id: task_row.assignee_id.clone(),
+        ref_id: context.ref_id.clone(),
+        assigned_at: task_row.updated_at,
+    }
+}
+
+// a task created with an assignee is assigned to them from nobody
+fn new_assignment(task_row: &Task, context: &AuditContext) -> Option<TaskAssigned> {
+    task_row
+        .assignee_id
+        .is_some()
+        .then(|| assigned(task_row, None, context))
+}
+
+// the parent has to exist and the user has to be able to edit it, the share
+// lock keeps it from being deleted until the task is written
+fn lock_parent(connection: &mut PgConnection, parent: Uuid, user_id: &str) -> Result<(), AppError> {
+    let parent_row = task
+        .filter(id.eq(parent))
+        .filter(deleted_at.is_null())
+        .select(Task::as_select())
+        .for_share()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::Validation(format!("parent_id {} does not exist", parent)))?;
+
+    match require_role(connection, user_id, &parent_row, ShareRole::Editor) {
+        Ok(_) => Ok(()),
+        // a parent the user cannot see is reported like a missing one
+        Err(AppError::NotFound(_)) => Err(AppError::Validation(format!(
+            "parent_id {} does not exist",
+            parent
+        ))),
+        Err(AppError::Forbidden(_)) => Err(AppError::Forbidden(format!(
+            "parent_id {} is shared with you as viewer, subtasks need an editor",
+            parent
+        ))),
+        Err(err) => Err(err),
+    }
+}
+
+// completes the auto-completing ancestors whose last open subtask was just
+// completed, and returns their ids
+fn complete_parents(
+    connection: &mut PgConnection,
+    child: &Task,
+    context: &AuditContext,
+) -> Result<Vec<Uuid>, AppError> {
+    let now = Utc::now();
+    let mut completed_ids = Vec::new();
+    let mut next = child.parent_id;
+
+    while let Some(parent) = next {
+        // the row lock serialises siblings completed at the same time, so
+        // the last of them sees every other sibling completed
+        let row: Option<(bool, bool, Option<Uuid>)> = task
+            .filter(id.eq(parent))
+            .select((completed, auto_complete, parent_id))
+            .for_update()
+            .first(connection)
+            .optional()?;
+
+        let Some((is_completed, is_auto_complete, grandparent)) = row else {
+            break;
+        };
+        if is_completed
+            || !is_auto_complete
+            || !find_open_blocker_ids(connection, parent)?.is_empty()
         {
-            Ok(_) => Ok(()),
-            Err(err) => Err(Box::new(err)),
+            break;
+        }
+
+        let open: i64 = task
+            .filter(parent_id.eq(parent))
+            .filter(completed.eq(false))
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(connection)?;
+        if open > 0 {
+            break;
         }
+
+        let before = load_states(connection, &[parent])?;
+        update(task)
+            .filter(id.eq(parent))
+            .set((
+                completed.eq(true),
+                completed_at.eq(now),
+                updated_at.eq(now),
+                version.eq(version + 1),
+            ))
+            .execute(connection)?;
+        record_changes(connection, &[parent], &before, TaskAction::Updated, context)?;
+
+        completed_ids.push(parent);
+        next = grandparent;
+    }
+
+    Ok(completed_ids)
+}
+
+// ids of every task below the given ones that was trashed at the given time,
+// or is not in the trash when the time is None
+fn find_tree_ids(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+    trashed_at: Option<DateTime<Utc>>,
+) -> Result<Vec<Uuid>, AppError> {
+    let tree: Vec<TaskId> = sql_query(
+        "WITH RECURSIVE tree AS ( \
+             SELECT id, 0 AS depth FROM task \
+             WHERE parent_id = ANY($1) AND deleted_at IS NOT DISTINCT FROM $2 \
+             UNION ALL \
+             SELECT task.id, tree.depth + 1 FROM task JOIN tree ON task.parent_id = tree.id \
+             WHERE task.deleted_at IS NOT DISTINCT FROM $2 AND tree.depth < $3 \
+         ) \
+         SELECT id AS task_id FROM tree",
+    )
+    .bind::<sql_types::Array<sql_types::Uuid>, _>(task_ids)
+    .bind::<sql_types::Nullable<sql_types::Timestamptz>, _>(trashed_at)
+    .bind::<sql_types::Integer, _>(MAX_TREE_DEPTH)
+    .load(connection)?;
+
+    Ok(tree.into_iter().map(|row| row.task_id).collect())
+}
+
+// moves the tasks and every task below them to the trash, they share one
+// deleted_at so restoring the top task brings all of them back. Returns the
+// ids of the trashed tasks and of the tasks they no longer block
+pub fn trash_tasks(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+    now: DateTime<Utc>,
+    context: &AuditContext,
+) -> Result<Vec<Uuid>, AppError> {
+    let mut trashed_ids = find_tree_ids(connection, task_ids, None)?;
+    trashed_ids.extend_from_slice(task_ids);
+    // a task below another of the given tasks is found twice
+    trashed_ids.sort();
+    trashed_ids.dedup();
+    let before = load_states(connection, &trashed_ids)?;
+
+    update(task)
+        .filter(id.eq_any(&trashed_ids))
+        .set((
+            deleted_at.eq(now),
+            updated_at.eq(now),
+            version.eq(version + 1),
+        ))
+        .execute(connection)?;
+    record_changes(
+        connection,
+        &trashed_ids,
+        &before,
+        TaskAction::Deleted,
+        context,
+    )?;
+
+    let dependent_ids = find_dependent_ids(connection, &trashed_ids)?;
+    trashed_ids.extend(dependent_ids);
+
+    Ok(trashed_ids)
+}
+
+// replaces the tags of a task, creating the owner's tags that do not exist yet
+fn set_tags(
+    connection: &mut PgConnection,
+    task_id: Uuid,
+    user_id: &str,
+    names: &[String],
+) -> Result<Vec<String>, AppError> {
+    delete(schema::task_tag::table)
+        .filter(schema::task_tag::task_id.eq(task_id))
+        .execute(connection)?;
+
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now();
+    let new_tags: Vec<Tag> = names
+        .iter()
+        .map(|name| Tag {
+            id: Uuid::new_v4(),
+            owner: user_id.to_string(),
+            name: name.clone(),
+            created_at: now,
+        })
+        .collect();
+    insert_into(schema::tag::table)
+        .values(&new_tags)
+        .on_conflict((schema::tag::owner, schema::tag::name))
+        .do_nothing()
+        .execute(connection)?;
+
+    let tags: Vec<Tag> = schema::tag::table
+        .filter(schema::tag::owner.eq(user_id))
+        .filter(schema::tag::name.eq_any(names))
+        .order(schema::tag::name.asc())
+        .select(Tag::as_select())
+        .load(connection)?;
+
+    let task_tags: Vec<TaskTag> = tags
+        .iter()
+        .map(|tag| TaskTag {
+            task_id,
+            tag_id: tag.id,
+        })
+        .collect();
+    insert_into(schema::task_tag::table)
+        .values(&task_tags)
+        .execute(connection)?;
+
+    Ok(tags.into_iter().map(|tag| tag.name).collect())
+}
+
+pub fn load_tags(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, AppError> {
+    let rows: Vec<(Uuid, String)> = schema::task_tag::table
+        .inner_join(schema::tag::table)
+        .filter(schema::task_tag::task_id.eq_any(task_ids))
+        .order(schema::tag::name.asc())
+        .select((schema::task_tag::task_id, schema::tag::name))
+        .load(connection)?;
+
+    let mut tags: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (task_id, name) in rows {
+        tags.entry(task_id).or_default().push(name);
+    }
+
+    Ok(tags)
+}
+
+// ids of the given tasks that still wait on an open blocker
+fn load_blocked(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+) -> Result<HashSet<Uuid>, AppError> {
+    let open_tasks = task
+        .filter(completed.eq(false))
+        .filter(deleted_at.is_null())
+        .select(id);
+    let blocked: Vec<Uuid> = schema::task_dependency::table
+        .filter(schema::task_dependency::task_id.eq_any(task_ids))
+        .filter(schema::task_dependency::blocker_id.eq_any(open_tasks))
+        .select(schema::task_dependency::task_id)
+        .distinct()
+        .load(connection)?;
+
+    Ok(blocked.into_iter().collect())
+}
+
+fn find_open_blocker_ids(
+    connection: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<Vec<Uuid>, AppError> {
+    let open_tasks = task
+        .filter(completed.eq(false))
+        .filter(deleted_at.is_null())
+        .select(id);
+    let blocker_ids = schema::task_dependency::table
+        .filter(schema::task_dependency::task_id.eq(task_id))
+        .filter(schema::task_dependency::blocker_id.eq_any(open_tasks))
+        .order(schema::task_dependency::blocker_id.asc())
+        .select(schema::task_dependency::blocker_id)
+        .load(connection)?;
+
+    Ok(blocker_ids)
+}
+
+// ids of the tasks directly blocked by any of the given tasks
+fn find_dependent_ids(
+    connection: &mut PgConnection,
+    task_ids: &[Uuid],
+) -> Result<Vec<Uuid>, AppError> {
+    // blocked is part of the task clients see, so whatever may have changed
+    // it gets a new version
+    let dependent_ids = update(task)
+        .filter(
+            id.eq_any(
+                schema::task_dependency::table
+                    .filter(schema::task_dependency::blocker_id.eq_any(task_ids))
+                    .select(schema::task_dependency::task_id),
+            ),
+        )
+        .set(version.eq(version + 1))
+        .returning(id)
+        .get_results(connection)?;
+
+    Ok(dependent_ids)
+}
+
+// returns the task as written, its owner, the ids of every other task of
+// the owner whose cache the write changes and the assignment it made
+fn apply_write(
+    connection: &mut PgConnection,
+    write: TaskWrite,
+    user_id: &str,
+    context: &AuditContext,
+) -> Result<WriteOutcome, AppError> {
+    match write {
+        TaskWrite::Insert {
+            task: new_task,
+            tags,
+        } => {
+            let result = apply_insert(connection, new_task, &tags, user_id, context)?;
+            let task_owner = result.task.owner.clone();
+            let assignment = new_assignment(&result.task, context);
+            Ok((Some(result), task_owner, Vec::new(), assignment))
+        }
+        TaskWrite::Update {
+            task: update_task,
+            tags,
+            next_occurrence,
+        } => {
+            let next_occurrence = next_occurrence.map(|next_occurrence| *next_occurrence);
+            let (result, changed_ids, assignment) = apply_update(
+                connection,
+                update_task,
+                tags,
+                next_occurrence,
+                user_id,
+                context,
+            )?;
+            let task_owner = result.task.owner.clone();
+            Ok((Some(result), task_owner, changed_ids, assignment))
+        }
+        TaskWrite::Delete {
+            task_id,
+            mode,
+            expected_versions,
+        } => {
+            let (task_owner, changed_ids) = apply_delete(
+                connection,
+                task_id,
+                user_id,
+                mode,
+                expected_versions,
+                context,
+            )?;
+            Ok((None, task_owner, changed_ids, None))
+        }
+    }
+}
+
+fn apply_insert(
+    connection: &mut PgConnection,
+    mut new_task: Task,
+    tags: &[String],
+    user_id: &str,
+    context: &AuditContext,
+) -> Result<TaskDetail, AppError> {
+    // a task added to a shared project belongs to the owner of the project
+    if let Some(project) = new_task.project_id {
+        new_task.owner = lock_project(connection, project, user_id)?;
+    }
+    if let Some(parent) = new_task.parent_id {
+        lock_parent(connection, parent, user_id)?;
+    }
+    check_assignee(connection, &new_task)?;
+
+    insert_into(task).values(&new_task).execute(connection)?;
+    let tags = set_tags(connection, new_task.id, &new_task.owner, tags)?;
+    record_changes(
+        connection,
+        &[new_task.id],
+        &TaskStates::new(),
+        TaskAction::Created,
+        context,
+    )?;
+
+    // a new task has no blockers yet
+    let role = require_role(connection, user_id, &new_task, ShareRole::Editor)?;
+    Ok(TaskDetail {
+        task: new_task,
+        tags,
+        blocked: false,
+        role,
+        next_occurrence_id: None,
+    })
+}
+
+// returns the updated task, the ids of every task whose cache it changes and
+// the assignment it made
+fn apply_update(
+    connection: &mut PgConnection,
+    update_task: Task,
+    tags: Option<Vec<String>>,
+    next_occurrence: Option<Task>,
+    user_id: &str,
+    context: &AuditContext,
+) -> Result<(TaskDetail, Vec<Uuid>, Option<TaskAssigned>), AppError> {
+    let current = task
+        .filter(id.eq(&update_task.id))
+        .filter(owner.eq(&update_task.owner))
+        .filter(deleted_at.is_null())
+        .select(Task::as_select())
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("task {} not found", update_task.id)))?;
+    require_role(connection, user_id, &current, ShareRole::Editor)?;
+
+    // the task carries its next version, a stale one fails here before
+    // anything is locked, a write racing this one is caught by the update
+    let expected_version = update_task.version - 1;
+    if current.version != expected_version {
+        return Err(changed_by_another_request(update_task.id));
+    }
+
+    // the task stays with its owner, so it can only move to another project
+    // of the owner that the user can edit
+    if let Some(project) = update_task.project_id {
+        let project_user = if update_task.project_id == current.project_id {
+            &update_task.owner
+        } else {
+            user_id
+        };
+        if lock_project(connection, project, project_user)? != update_task.owner {
+            return Err(AppError::Validation(format!(
+                "project_id {} belongs to another user",
+                project
+            )));
+        }
+    }
+    // a parent the task already had is checked as its owner, a new one as
+    // the user moving the task
+    if let Some(parent) = update_task.parent_id {
+        let parent_user = if update_task.parent_id == current.parent_id {
+            &update_task.owner
+        } else {
+            user_id
+        };
+        lock_parent(connection, parent, parent_user)?;
+    }
+    // an assignee keeps the task when their access is revoked later, the
+    // check is only made when the task is handed over
+    let reassigned = update_task.assignee_id != current.assignee_id;
+    if reassigned {
+        check_assignee(connection, &update_task)?;
+    }
+
+    // the row is only written when it still has the version that was read,
+    // so nothing read before the update can be stale once it succeeds
+    let before = load_states(connection, &[update_task.id])?;
+    let updated = update(task)
+        .filter(id.eq(&update_task.id))
+        .filter(version.eq(expected_version))
+        .set(&update_task)
+        .execute(connection)?;
+    if updated == 0 {
+        return Err(changed_by_another_request(update_task.id));
+    }
+
+    // tags are left as they are unless the caller replaces them
+    if let Some(tags) = tags {
+        set_tags(connection, update_task.id, &update_task.owner, &tags)?;
+    }
+    let action = if reassigned {
+        TaskAction::Assigned
+    } else {
+        TaskAction::Updated
+    };
+    record_changes(connection, &[update_task.id], &before, action, context)?;
+    let assignment = reassigned.then(|| assigned(&update_task, current.assignee_id, context));
+
+    let mut changed_ids = vec![update_task.id];
+    if update_task.completed {
+        changed_ids.extend(complete_parents(connection, &update_task, context)?);
+    }
+    // completing or reopening a task changes whether its dependents are blocked
+    let dependent_ids = find_dependent_ids(connection, &changed_ids)?;
+    changed_ids.extend(dependent_ids);
+
+    let mut result = with_details(connection, user_id, vec![update_task])?
+        .pop()
+        .unwrap_or_default();
+
+    // the next occurrence of a recurring task keeps its tags
+    if let Some(next_occurrence) = next_occurrence {
+        insert_into(task)
+            .values(&next_occurrence)
+            .execute(connection)?;
+        set_tags(
+            connection,
+            next_occurrence.id,
+            &next_occurrence.owner,
+            &result.tags,
+        )?;
+        record_changes(
+            connection,
+            &[next_occurrence.id],
+            &TaskStates::new(),
+            TaskAction::Created,
+            context,
+        )?;
+        result.next_occurrence_id = Some(next_occurrence.id);
+    }
+
+    Ok((result, changed_ids, assignment))
+}
+
+// returns the owner of the task and the ids of every task whose cache the
+// delete changes
+fn apply_delete(
+    connection: &mut PgConnection,
+    task_id: Uuid,
+    user_id: &str,
+    mode: SubtaskDeleteMode,
+    expected_versions: Option<Vec<i32>>,
+    context: &AuditContext,
+) -> Result<(String, Vec<Uuid>), AppError> {
+    let deleted = task
+        .filter(id.eq(task_id))
+        .filter(accessible(user_id, ShareRole::Viewer))
+        .filter(deleted_at.is_null())
+        .select(Task::as_select())
+        .for_update()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+    require_role(connection, user_id, &deleted, ShareRole::Owner)?;
+    if let Some(expected_versions) = expected_versions {
+        if !expected_versions.contains(&deleted.version) {
+            return Err(changed_by_another_request(task_id));
+        }
+    }
+
+    let now = Utc::now();
+    let mut changed_ids: Vec<Uuid> = match mode {
+        SubtaskDeleteMode::Cascade => Vec::new(),
+        SubtaskDeleteMode::Promote => {
+            let child_ids: Vec<Uuid> = task
+                .filter(parent_id.eq(task_id))
+                .filter(deleted_at.is_null())
+                .select(id)
+                .load(connection)?;
+            let before = load_states(connection, &child_ids)?;
+
+            update(task)
+                .filter(id.eq_any(&child_ids))
+                .set((
+                    parent_id.eq(deleted.parent_id),
+                    updated_at.eq(now),
+                    version.eq(version + 1),
+                ))
+                .execute(connection)?;
+            record_changes(
+                connection,
+                &child_ids,
+                &before,
+                TaskAction::Updated,
+                context,
+            )?;
+
+            child_ids
+        }
+    };
+
+    // with the children promoted, the task is trashed on its own
+    changed_ids.extend(trash_tasks(connection, &[task_id], now, context)?);
+
+    Ok((deleted.owner, changed_ids))
+}
+
+// an expired key can be used again, anything else means another request
+// with the key got there first
+fn insert_idempotency_key(
+    connection: &mut PgConnection,
+    idempotency_key: &IdempotencyKey,
+) -> Result<(), AppError> {
+    delete(schema::idempotency_key::table)
+        .filter(schema::idempotency_key::owner.eq(&idempotency_key.owner))
+        .filter(schema::idempotency_key::expires_at.le(idempotency_key.created_at))
+        .execute(connection)?;
+
+    let inserted = insert_into(schema::idempotency_key::table)
+        .values(idempotency_key)
+        .on_conflict_do_nothing()
+        .execute(connection)?;
+    if inserted == 0 {
+        return Err(AppError::Conflict(format!(
+            "Idempotency-Key {} is already in use",
+            idempotency_key.key
+        )));
+    }
+
+    Ok(())
+}
+
+fn changed_by_another_request(task_id: Uuid) -> AppError {
+    AppError::PreconditionFailed(format!("task {} was changed by another request", task_id))
+}
+
+// for writes that leave the task row as it is, like its blockers changing
+fn bump_version(connection: &mut PgConnection, task_id: Uuid) -> Result<Task, AppError> {
+    let bumped = update(task)
+        .filter(id.eq(task_id))
+        .set(version.eq(version + 1))
+        .returning(Task::as_returning())
+        .get_result(connection)?;
+
+    Ok(bumped)
+}
+
+// moves the tasks on to their next version without writing anything else,
+// for changes made to rows they are returned with
+pub fn bump_versions(connection: &mut PgConnection, task_ids: &[Uuid]) -> Result<(), AppError> {
+    update(task)
+        .filter(id.eq_any(task_ids))
+        .set(version.eq(version + 1))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+// the tasks as the given user sees them
+fn with_details(
+    connection: &mut PgConnection,
+    user_id: &str,
+    tasks: Vec<Task>,
+) -> Result<Vec<TaskDetail>, AppError> {
+    let task_ids: Vec<Uuid> = tasks.iter().map(|task_row| task_row.id).collect();
+    let mut tags = load_tags(connection, &task_ids)?;
+    let blocked = load_blocked(connection, &task_ids)?;
+    let roles = load_roles(connection, user_id, &tasks)?;
+
+    Ok(tasks
+        .into_iter()
+        .map(|task_row| TaskDetail {
+            tags: tags.remove(&task_row.id).unwrap_or_default(),
+            blocked: blocked.contains(&task_row.id),
+            role: roles.get(&task_row.id).copied().unwrap_or_default(),
+            next_occurrence_id: None,
+            task: task_row,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::model;
+    use crate::database::testing::{cache, database};
+    use crate::model::request::{TaskDeleteQuery, TaskQuery, TaskRequest};
+    use crate::service::interface::TaskServiceInterface;
+    use crate::service::task_manager::TaskService;
+    use diesel::connection::SimpleConnection;
+    use diesel::dsl::sql;
+
+    fn filter() -> TaskFilter {
+        TaskFilter {
+            completed: None,
+            title: None,
+            due_after: None,
+            due_before: None,
+            overdue: false,
+            project: None,
+            parent_id: None,
+            assignee: None,
+            tags: Vec::new(),
+            tag_match: TagMatch::Any,
+            sort: TaskSort::Id,
+            order: SortOrder::Asc,
+            offset: 0,
+            limit: 10,
+        }
+    }
+
+    #[test]
+    fn next_cursor_points_past_the_page() {
+        assert_eq!(next_cursor(0, 10, 25), Some("10".to_string()));
+        assert_eq!(next_cursor(20, 5, 25), None);
+        assert_eq!(next_cursor(0, 0, 0), None);
+        // a short page that is not the last still points past what it returned
+        assert_eq!(next_cursor(20, 3, 25), Some("23".to_string()));
+    }
+
+    #[test]
+    fn list_key_changes_with_the_version() {
+        let filter = filter();
+        assert_ne!(
+            list_key("alice", 1, &filter).unwrap(),
+            list_key("alice", 2, &filter).unwrap()
+        );
+        assert_ne!(
+            list_key("alice", 1, &filter).unwrap(),
+            list_key("bob", 1, &filter).unwrap()
+        );
+    }
+
+    #[test]
+    fn list_key_changes_with_the_query() {
+        let first_page = filter();
+        let second_page = TaskFilter {
+            offset: 10,
+            ..filter()
+        };
+        let done = TaskFilter {
+            completed: Some(true),
+            ..filter()
+        };
+        let keys =
+            [&first_page, &second_page, &done].map(|filter| list_key("alice", 1, filter).unwrap());
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+        assert_ne!(keys[1], keys[2]);
+    }
+
+    #[test]
+    fn task_key_changes_with_the_version() {
+        let task_id = Uuid::new_v4();
+        assert_ne!(task_key("alice", task_id, 0), task_key("alice", task_id, 1));
+        // the version counter never collides with a versioned copy
+        assert_ne!(
+            task_version_key("alice", task_id),
+            task_key("alice", task_id, 0)
+        );
+        assert!(!task_version_key("alice", task_id).starts_with(&list_version_key("alice")));
+    }
+
+    fn request(task_title: &str) -> TaskRequest {
+        TaskRequest {
+            title: task_title.to_string(),
+            description: "cache test".to_string(),
+            completed: false,
+            due_at: None,
+            priority: None,
+            tags: None,
+            project_id: None,
+            parent_id: None,
+            auto_complete: None,
+            recurrence: None,
+            assignee_id: None,
+        }
+    }
+
+    async fn connect() -> (TaskService, Client) {
+        let redis_client = cache().await;
+        let repository = TaskRepository::new(database(), redis_client.clone(), EventBus::new());
+        let service = TaskService::new(
+            repository,
+            model::Task::default(),
+            model::Idempotency::default(),
+        );
+        (service, redis_client)
+    }
+
+    async fn titles(service: &TaskService, user_id: &str) -> Vec<String> {
+        service
+            .find_all(user_id.to_string(), TaskQuery::default())
+            .await
+            .unwrap()
+            .tasks
+            .into_iter()
+            .map(|detail| detail.task.title)
+            .collect()
+    }
+
+    // every list read is answered from the cache when it can be, so each
+    // write has to be seen by the read right after it
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn writes_are_visible_to_the_next_list() {
+        let (service, redis_client) = connect().await;
+        let user_id = format!("cache-test-{}", Uuid::new_v4());
+        let ref_id = Uuid::new_v4().to_string();
+
+        // an empty list, read twice so the second read is served from the cache
+        assert!(titles(&service, &user_id).await.is_empty());
+        assert!(titles(&service, &user_id).await.is_empty());
+        let list_version = redis_client
+            .get_counter(list_version_key(&user_id))
+            .await
+            .unwrap();
+
+        let created = service
+            .insert(request("first"), user_id.clone(), None, ref_id.clone())
+            .await
+            .unwrap();
+        let task_id = created.task.id.to_string();
+        assert_eq!(titles(&service, &user_id).await, vec!["first"]);
+        assert_eq!(
+            redis_client
+                .get_counter(list_version_key(&user_id))
+                .await
+                .unwrap(),
+            list_version + 1
+        );
+
+        // the task itself is cached on its first read
+        let found = service
+            .find_by_id(task_id.clone(), user_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(found.task.title, "first");
+
+        service
+            .update(
+                request("renamed"),
+                task_id.clone(),
+                user_id.clone(),
+                None,
+                ref_id.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(titles(&service, &user_id).await, vec!["renamed"]);
+        let found = service
+            .find_by_id(task_id.clone(), user_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(found.task.title, "renamed");
+
+        service
+            .delete(
+                TaskDeleteQuery { children: None },
+                task_id.clone(),
+                user_id.clone(),
+                None,
+                ref_id,
+            )
+            .await
+            .unwrap();
+        assert!(titles(&service, &user_id).await.is_empty());
+        assert!(matches!(
+            service.find_by_id(task_id, user_id).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    // the update reads the task before another writer commits, then has to
+    // find the version it read gone instead of writing over that commit
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn update_fails_when_the_version_moved_on() {
+        let (service, redis_client) = connect().await;
+        let user_id = format!("version-test-{}", Uuid::new_v4());
+        let ref_id = Uuid::new_v4().to_string();
+        let created = service
+            .insert(request("first"), user_id.clone(), None, ref_id.clone())
+            .await
+            .unwrap();
+        let task_id = created.task.id;
+        let created_version = created.task.version;
+
+        let db_pool = database();
+        let repository = TaskRepository::new(db_pool.clone(), redis_client, EventBus::new());
+
+        // the other writer holds the row with a new version until it commits
+        let mut writer = db_pool.get().unwrap();
+        writer.batch_execute("BEGIN").unwrap();
+        update(task)
+            .filter(id.eq(task_id))
+            .set(version.eq(version + 1))
+            .execute(&mut writer)
+            .unwrap();
+
+        let mut update_task = created.task;
+        update_task.title = "renamed".to_string();
+        update_task.version += 1;
+        let commit_when_blocked = async {
+            let mut observer = db_pool.get().unwrap();
+            while !select(sql::<sql_types::Bool>(
+                "EXISTS (SELECT 1 FROM pg_locks WHERE NOT granted)",
+            ))
+            .get_result::<bool>(&mut observer)
+            .unwrap()
+            {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            writer.batch_execute("COMMIT").unwrap();
+        };
+        let (result, _) = futures_util::join!(
+            repository.update(
+                update_task,
+                user_id.clone(),
+                None,
+                None,
+                AuditContext::new(&user_id, ref_id),
+            ),
+            commit_when_blocked
+        );
+        assert!(matches!(result, Err(AppError::PreconditionFailed(_))));
+
+        let found = service
+            .find_by_id(task_id.to_string(), user_id)
+            .await
+            .unwrap();
+        assert_eq!(found.task.title, "first");
+        assert_eq!(found.task.version, created_version + 1);
     }
 }
@@ -0,0 +1,21 @@
+use crate::handler::{self};
+use actix_web::web;
+
+pub fn config_route(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/task/{id}/attachment",
+        web::get().to(handler::attachment::get_attachment),
+    );
+    cfg.route(
+        "/task/{id}/attachment",
+        web::post().to(handler::attachment::create_attachment),
+    );
+    cfg.route(
+        "/task/{id}/attachment/{attachment_id}",
+        web::get().to(handler::attachment::download_attachment),
+    );
+    cfg.route(
+        "/task/{id}/attachment/{attachment_id}",
+        web::delete().to(handler::attachment::delete_attachment),
+    );
+}
@@ -0,0 +1,21 @@
+use crate::handler::{self};
+use actix_web::web;
+
+pub fn config_route(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/task/{id}/comment",
+        web::get().to(handler::comment::get_comment),
+    );
+    cfg.route(
+        "/task/{id}/comment",
+        web::post().to(handler::comment::create_comment),
+    );
+    cfg.route(
+        "/task/{id}/comment/{comment_id}",
+        web::put().to(handler::comment::update_comment),
+    );
+    cfg.route(
+        "/task/{id}/comment/{comment_id}",
+        web::delete().to(handler::comment::delete_comment),
+    );
+}
@@ -1 +1,6 @@
+pub mod attachment;
+pub mod comment;
+pub mod project;
+pub mod share;
+pub mod tag;
 pub mod task_manager;
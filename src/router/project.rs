@@ -0,0 +1,23 @@
+use crate::handler::{self};
+use actix_web::web;
+
+pub fn config_route(cfg: &mut web::ServiceConfig) {
+    cfg.route("/project", web::post().to(handler::project::create_project));
+    cfg.route("/project", web::get().to(handler::project::get_project));
+    cfg.route(
+        "/project/{id}",
+        web::get().to(handler::project::get_project_by_id),
+    );
+    cfg.route(
+        "/project/{id}",
+        web::put().to(handler::project::update_project_by_id),
+    );
+    cfg.route(
+        "/project/{id}",
+        web::delete().to(handler::project::delete_project_by_id),
+    );
+    cfg.route(
+        "/project/{id}/task",
+        web::get().to(handler::project::get_project_task),
+    );
+}
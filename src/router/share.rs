@@ -0,0 +1,37 @@
+use crate::handler::{self};
+use actix_web::web;
+
+pub fn config_route(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/task/{id}/share",
+        web::get().to(handler::share::get_task_share),
+    );
+    cfg.route(
+        "/task/{id}/share",
+        web::post().to(handler::share::create_task_share),
+    );
+    cfg.route(
+        "/task/{id}/share/{user_id}",
+        web::put().to(handler::share::update_task_share),
+    );
+    cfg.route(
+        "/task/{id}/share/{user_id}",
+        web::delete().to(handler::share::delete_task_share),
+    );
+    cfg.route(
+        "/project/{id}/share",
+        web::get().to(handler::share::get_project_share),
+    );
+    cfg.route(
+        "/project/{id}/share",
+        web::post().to(handler::share::create_project_share),
+    );
+    cfg.route(
+        "/project/{id}/share/{user_id}",
+        web::put().to(handler::share::update_project_share),
+    );
+    cfg.route(
+        "/project/{id}/share/{user_id}",
+        web::delete().to(handler::share::delete_project_share),
+    );
+}
@@ -0,0 +1,12 @@
+use crate::handler::{self};
+use actix_web::web;
+
+pub fn config_route(cfg: &mut web::ServiceConfig) {
+    cfg.route("/tag", web::post().to(handler::tag::create_tag));
+    cfg.route("/tag", web::get().to(handler::tag::get_tag));
+    cfg.route("/tag/{id}", web::put().to(handler::tag::update_tag_by_id));
+    cfg.route(
+        "/tag/{id}",
+        web::delete().to(handler::tag::delete_tag_by_id),
+    );
+}
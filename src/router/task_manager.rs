@@ -1,9 +1,47 @@
 use crate::handler::{self};
+use crate::model::error::AppError;
+use actix_web::error::JsonPayloadError;
 use actix_web::web;
 
 pub fn config_route(cfg: &mut web::ServiceConfig) {
+    // PATCH bodies are sent as application/merge-patch+json, actix answers
+    // any other content type with a 400 unless told otherwise
+    cfg.app_data(
+        web::JsonConfig::default()
+            .content_type(|mime| {
+                matches!(
+                    mime.essence_str(),
+                    "application/json" | "application/merge-patch+json"
+                )
+            })
+            .error_handler(|err, _| match err {
+                JsonPayloadError::ContentType => AppError::UnsupportedMediaType(
+                    "expected application/json or application/merge-patch+json".to_string(),
+                )
+                .into(),
+                err => err.into(),
+            }),
+    );
+
     cfg.route("/task", web::post().to(handler::task_manager::create_task));
     cfg.route("/task", web::get().to(handler::task_manager::get_task));
+    // static segments must be registered before /task/{id}
+    cfg.route(
+        "/task/overdue",
+        web::get().to(handler::task_manager::get_overdue_task),
+    );
+    cfg.route(
+        "/task/due",
+        web::get().to(handler::task_manager::get_due_task),
+    );
+    cfg.route(
+        "/task/search",
+        web::get().to(handler::task_manager::search_task),
+    );
+    cfg.route(
+        "/task/bulk",
+        web::post().to(handler::task_manager::bulk_task),
+    );
     cfg.route(
         "/task/{id}",
         web::get().to(handler::task_manager::get_task_by_id),
@@ -12,8 +50,68 @@ pub fn config_route(cfg: &mut web::ServiceConfig) {
         "/task/{id}",
         web::put().to(handler::task_manager::update_task_by_id),
     );
+    cfg.route(
+        "/task/{id}",
+        web::patch().to(handler::task_manager::patch_task_by_id),
+    );
     cfg.route(
         "/task/{id}",
         web::delete().to(handler::task_manager::delete_task_by_id),
     );
+    cfg.route(
+        "/task/{id}/children",
+        web::get().to(handler::task_manager::get_task_children),
+    );
+    cfg.route(
+        "/task/{id}/tree",
+        web::get().to(handler::task_manager::get_task_tree),
+    );
+    cfg.route(
+        "/task/{id}/occurrences",
+        web::get().to(handler::task_manager::get_task_occurrences),
+    );
+    cfg.route(
+        "/task/{id}/dependencies",
+        web::get().to(handler::task_manager::get_task_dependencies),
+    );
+    cfg.route(
+        "/task/{id}/blocker",
+        web::post().to(handler::task_manager::add_task_blocker),
+    );
+    cfg.route(
+        "/task/{id}/blocker/{blocker_id}",
+        web::delete().to(handler::task_manager::delete_task_blocker),
+    );
+    cfg.route(
+        "/task/{id}/restore",
+        web::post().to(handler::task_manager::restore_task_by_id),
+    );
+    cfg.route(
+        "/task/{id}/history",
+        web::get().to(handler::task_manager::get_task_history),
+    );
+    cfg.route("/trash", web::get().to(handler::task_manager::get_trash));
+    cfg.route(
+        "/activity",
+        web::get().to(handler::task_manager::get_activity),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn patch_rejects_other_content_types() {
+        let app = test::init_service(App::new().configure(config_route)).await;
+        let request = test::TestRequest::patch()
+            .uri("/task/7b8bd4f2-93a3-4c4e-8a52-3e0d5b0c1e5f")
+            .insert_header((header::CONTENT_TYPE, "text/plain"))
+            .set_payload(r#"{"title":"renamed"}"#)
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
 }
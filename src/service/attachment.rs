@@ -0,0 +1,443 @@
+use chrono::Utc;
+use futures_util::{stream, StreamExt};
+use log::error;
+use uuid::Uuid;
+
+use crate::configuration::model::Attachment as AttachmentConfig;
+use crate::model::attachment::{Attachment, Download, Upload};
+use crate::model::error::AppError;
+use crate::repository::attachment::AttachmentRepository;
+use crate::repository::interface::AttachmentRepositoryInterface;
+use crate::service::interface::AttachmentServiceInterface;
+use crate::service::task_manager::parse_task_id;
+use crate::storage::BlobStorage;
+use async_trait::async_trait;
+
+const MAX_FILE_NAME_LENGTH: usize = 255;
+const ORPHANED_BLOB_BATCH: i64 = 100;
+
+// the type of a part that does not say what it is
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+pub struct AttachmentService {
+    repository: AttachmentRepository,
+    storage: Box<dyn BlobStorage>,
+    config: AttachmentConfig,
+}
+
+impl AttachmentService {
+    pub fn new(
+        repository: AttachmentRepository,
+        storage: Box<dyn BlobStorage>,
+        config: AttachmentConfig,
+    ) -> Self {
+        AttachmentService {
+            repository,
+            storage,
+            config,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl AttachmentServiceInterface for AttachmentService {
+    async fn insert(
+        &self,
+        upload: Upload,
+        task_id: String,
+        user_id: String,
+    ) -> Result<Attachment, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let file_name = parse_file_name(upload.file_name)?;
+        let content_type = parse_content_type(upload.content_type, &self.config.allowed_types)?;
+
+        // nothing is stored for a task the user cannot edit
+        self.repository
+            .check_upload(task_id, user_id.clone())
+            .await?;
+
+        let attachment_id = Uuid::new_v4();
+        let key = storage_key(attachment_id);
+        let blob = self
+            .storage
+            .put(&key, upload.body, self.config.max_size)
+            .await?;
+
+        let attachment = Attachment {
+            id: attachment_id,
+            task_id,
+            uploader: user_id,
+            file_name,
+            content_type,
+            size: blob.size as i64,
+            checksum: blob.checksum,
+            storage_key: key.clone(),
+            created_at: Utc::now(),
+        };
+
+        match self.repository.insert(attachment).await {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                // the row never made it, so the trigger will not queue the blob
+                if let Err(delete_err) = self.storage.delete(&key).await {
+                    error!("delete blob {} error: {:?}", key, delete_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn find_all(
+        &self,
+        task_id: String,
+        user_id: String,
+    ) -> Result<Vec<Attachment>, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+
+        let attachments = self.repository.find_all(task_id, user_id).await?;
+        Ok(attachments)
+    }
+
+    async fn download(
+        &self,
+        task_id: String,
+        attachment_id: String,
+        user_id: String,
+        range: Option<String>,
+        if_range: Option<String>,
+    ) -> Result<Download, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let attachment_id = parse_attachment_id(&attachment_id)?;
+
+        let attachment = self
+            .repository
+            .find_by_id(attachment_id, task_id, user_id)
+            .await?;
+        let size = attachment.size as u64;
+
+        let range = select_range(range, if_range, &attachment.etag(), size)?;
+
+        let body = match (range, size) {
+            (_, 0) => stream::empty().boxed_local(),
+            (Some((start, end)), _) => {
+                self.storage
+                    .get(&attachment.storage_key, start, end)
+                    .await?
+            }
+            (None, _) => {
+                self.storage
+                    .get(&attachment.storage_key, 0, size - 1)
+                    .await?
+            }
+        };
+
+        Ok(Download {
+            attachment,
+            range,
+            body,
+        })
+    }
+
+    async fn delete(
+        &self,
+        task_id: String,
+        attachment_id: String,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let attachment_id = parse_attachment_id(&attachment_id)?;
+
+        let key = self
+            .repository
+            .delete(attachment_id, task_id, user_id)
+            .await?;
+
+        // the blob is queued already, whatever fails here is retried by the cleanup job
+        match self.storage.delete(&key).await {
+            Ok(_) => {
+                if let Err(err) = self.repository.delete_orphaned_blobs(vec![key]).await {
+                    error!("dequeue orphaned blob error: {:?}", err);
+                }
+            }
+            Err(err) => error!("delete blob {} error: {:?}", key, err),
+        }
+
+        Ok(())
+    }
+
+    async fn delete_orphaned_blobs(&self) -> Result<usize, AppError> {
+        let mut deleted = 0;
+
+        // batch after batch until the queue is empty or nothing can be deleted
+        loop {
+            let keys = self
+                .repository
+                .find_orphaned_blobs(ORPHANED_BLOB_BATCH)
+                .await?;
+
+            let mut batch = Vec::with_capacity(keys.len());
+            for key in &keys {
+                match self.storage.delete(key).await {
+                    Ok(_) => batch.push(key.clone()),
+                    Err(err) => error!("delete blob {} error: {:?}", key, err),
+                }
+            }
+            if batch.is_empty() {
+                return Ok(deleted);
+            }
+
+            deleted += batch.len();
+            self.repository.delete_orphaned_blobs(batch).await?;
+
+            if (keys.len() as i64) < ORPHANED_BLOB_BATCH {
+                return Ok(deleted);
+            }
+        }
+    }
+}
+
+// blobs are spread over directories by the first characters of their id
+fn storage_key(attachment_id: Uuid) -> String {
+    let id = attachment_id.to_string();
+    format!("{}/{}", &id[..2], id)
+}
+
+fn parse_attachment_id(attachment_id: &str) -> Result<Uuid, AppError> {
+    if attachment_id.is_empty() {
+        return Err(AppError::Validation(
+            "attachment_id cannot be empty".to_string(),
+        ));
+    }
+
+    Uuid::parse_str(attachment_id).map_err(|_| {
+        AppError::Validation(format!(
+            "attachment_id {} is not a valid uuid",
+            attachment_id
+        ))
+    })
+}
+
+// only the last segment of a path is kept, some clients send the full path
+// of the file they uploaded
+fn parse_file_name(file_name: Option<String>) -> Result<String, AppError> {
+    let file_name = file_name.unwrap_or_default();
+    let file_name: String = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    let file_name = file_name.trim();
+
+    if file_name.is_empty() {
+        return Err(AppError::Validation(
+            "file name cannot be empty".to_string(),
+        ));
+    }
+
+    if file_name.chars().count() > MAX_FILE_NAME_LENGTH {
+        return Err(AppError::Validation(format!(
+            "file name cannot be longer than {} characters",
+            MAX_FILE_NAME_LENGTH
+        )));
+    }
+
+    Ok(file_name.to_string())
+}
+
+// the type without its parameters, checked against the allowed types where
+// type/* allows every subtype and an empty list allows anything
+fn parse_content_type(
+    content_type: Option<String>,
+    allowed_types: &[String],
+) -> Result<String, AppError> {
+    let content_type = content_type.unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let (kind, _) = content_type.split_once('/').ok_or_else(|| {
+        AppError::Validation(format!("content type {} is not valid", content_type))
+    })?;
+
+    let allowed = allowed_types.is_empty()
+        || allowed_types.iter().any(|allowed| {
+            allowed.eq_ignore_ascii_case(&content_type)
+                || allowed
+                    .strip_suffix("/*")
+                    .is_some_and(|allowed| allowed.eq_ignore_ascii_case(kind))
+        });
+    if !allowed {
+        return Err(AppError::UnsupportedMediaType(format!(
+            "content type {} is not allowed",
+            content_type
+        )));
+    }
+
+    Ok(content_type)
+}
+
+// a range of a different version than the client has would be garbage to it,
+// so an If-Range that is not the current ETag gets the whole blob
+fn select_range(
+    range: Option<String>,
+    if_range: Option<String>,
+    etag: &str,
+    size: u64,
+) -> Result<Option<(u64, u64)>, AppError> {
+    match (range, if_range) {
+        (Some(_), Some(if_range)) if if_range != etag => Ok(None),
+        (Some(range), _) => parse_range(&range, size),
+        (None, _) => Ok(None),
+    }
+}
+
+// a single range of bytes as first and last byte. Several ranges, or one
+// that cannot be parsed, are served in full as the spec allows
+fn parse_range(range: &str, size: u64) -> Result<Option<(u64, u64)>, AppError> {
+    let spec = match range.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return Ok(None),
+    };
+    let (first, last) = match spec.split_once('-') {
+        Some((first, last)) => (first.trim(), last.trim()),
+        None => return Ok(None),
+    };
+
+    // the last bytes of the blob
+    if first.is_empty() {
+        let suffix = match last.parse::<u64>() {
+            Ok(suffix) => suffix,
+            Err(_) => return Ok(None),
+        };
+        if suffix == 0 || size == 0 {
+            return Err(AppError::RangeNotSatisfiable(size));
+        }
+        return Ok(Some((size - suffix.min(size), size - 1)));
+    }
+
+    let start = match first.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return Ok(None),
+    };
+    let end = match last {
+        "" => u64::MAX,
+        last => match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return Ok(None),
+        },
+    };
+    if start >= size {
+        return Err(AppError::RangeNotSatisfiable(size));
+    }
+
+    Ok(Some((start, end.min(size - 1))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(range: &str, size: u64) -> Option<(u64, u64)> {
+        parse_range(range, size).unwrap()
+    }
+
+    fn is_not_satisfiable(range: &str, size: u64) -> bool {
+        matches!(
+            parse_range(range, size),
+            Err(AppError::RangeNotSatisfiable(blob_size)) if blob_size == size
+        )
+    }
+
+    #[test]
+    fn range_is_clamped_to_the_blob() {
+        assert_eq!(range("bytes=0-", 10), Some((0, 9)));
+        assert_eq!(range("bytes=2-5", 10), Some((2, 5)));
+        assert_eq!(range("bytes=2-100", 10), Some((2, 9)));
+        assert_eq!(range("bytes=9-9", 10), Some((9, 9)));
+        assert_eq!(range(" bytes= 2 - 5 ", 10), Some((2, 5)));
+    }
+
+    #[test]
+    fn suffix_range_is_the_last_bytes() {
+        assert_eq!(range("bytes=-3", 10), Some((7, 9)));
+        assert_eq!(range("bytes=-10", 10), Some((0, 9)));
+        // a suffix longer than the blob is all of it
+        assert_eq!(range("bytes=-25", 10), Some((0, 9)));
+    }
+
+    #[test]
+    fn range_outside_the_blob_is_not_satisfiable() {
+        assert!(is_not_satisfiable("bytes=-0", 10));
+        assert!(is_not_satisfiable("bytes=10-", 10));
+        assert!(is_not_satisfiable("bytes=15-20", 10));
+        // an empty blob has no byte to start at
+        assert!(is_not_satisfiable("bytes=0-", 0));
+        assert!(is_not_satisfiable("bytes=-5", 0));
+    }
+
+    #[test]
+    fn unusable_ranges_get_the_whole_blob() {
+        for unusable in [
+            "bytes=5-3",
+            "bytes=0-1,3-4",
+            "bytes=0-1, 8-",
+            "items=0-5",
+            "0-5",
+            "bytes=5",
+            "bytes=a-b",
+            "bytes=-x",
+            "bytes=",
+        ] {
+            assert_eq!(range(unusable, 10), None, "{}", unusable);
+        }
+    }
+
+    #[test]
+    fn if_range_for_another_version_gets_the_whole_blob() {
+        let select = |range: Option<&str>, if_range: Option<&str>| {
+            select_range(
+                range.map(str::to_string),
+                if_range.map(str::to_string),
+                "\"abc\"",
+                10,
+            )
+            .unwrap()
+        };
+        assert_eq!(select(Some("bytes=2-5"), None), Some((2, 5)));
+        assert_eq!(select(Some("bytes=2-5"), Some("\"abc\"")), Some((2, 5)));
+        assert_eq!(select(Some("bytes=2-5"), Some("\"old\"")), None);
+        assert_eq!(select(Some("bytes=2-5"), Some("W/\"abc\"")), None);
+        assert_eq!(
+            select(Some("bytes=2-5"), Some("Wed, 21 Oct 2026 07:28:00 GMT")),
+            None
+        );
+        assert_eq!(select(None, Some("\"abc\"")), None);
+        // a stale If-Range also skips a range that could not be satisfied
+        assert_eq!(select(Some("bytes=20-"), Some("\"old\"")), None);
+    }
+}
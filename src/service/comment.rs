@@ -0,0 +1,155 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::model::comment::{Comment, CommentDetail, CommentPage};
+use crate::model::error::AppError;
+use crate::model::request::{CommentRequest, PageQuery};
+use crate::repository::comment::CommentRepository;
+use crate::repository::interface::CommentRepositoryInterface;
+use crate::service::interface::CommentServiceInterface;
+use crate::service::task_manager::{parse_page, parse_task_id};
+use crate::util::markdown;
+use async_trait::async_trait;
+
+const MAX_BODY_LENGTH: usize = 10000;
+
+pub struct CommentService {
+    repository: CommentRepository,
+}
+
+impl CommentService {
+    pub fn new(repository: CommentRepository) -> Self {
+        CommentService { repository }
+    }
+}
+
+#[async_trait]
+impl CommentServiceInterface for CommentService {
+    async fn insert(
+        &self,
+        comment_request: CommentRequest,
+        task_id: String,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let body = parse_body(comment_request.body)?;
+        let mentions = parse_mentions(&body, &user_id);
+
+        let now = Utc::now();
+        let comment = Comment {
+            id: Uuid::new_v4(),
+            task_id,
+            author: user_id,
+            body,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let result = self.repository.insert(comment, mentions).await?;
+        Ok(result)
+    }
+
+    async fn find_all(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<CommentPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+        let page = self
+            .repository
+            .find_all(task_id, user_id, offset, limit)
+            .await?;
+        Ok(page)
+    }
+
+    async fn update(
+        &self,
+        comment_request: CommentRequest,
+        task_id: String,
+        comment_id: String,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let comment_id = parse_comment_id(&comment_id)?;
+        let body = parse_body(comment_request.body)?;
+        let mentions = parse_mentions(&body, &user_id);
+
+        let result = self
+            .repository
+            .update(comment_id, task_id, body, mentions, user_id)
+            .await?;
+        Ok(result)
+    }
+
+    async fn delete(
+        &self,
+        task_id: String,
+        comment_id: String,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let comment_id = parse_comment_id(&comment_id)?;
+
+        self.repository.delete(comment_id, task_id, user_id).await?;
+        Ok(())
+    }
+}
+
+fn parse_comment_id(comment_id: &str) -> Result<Uuid, AppError> {
+    if comment_id.is_empty() {
+        return Err(AppError::Validation(
+            "comment_id cannot be empty".to_string(),
+        ));
+    }
+
+    Uuid::parse_str(comment_id)
+        .map_err(|_| AppError::Validation(format!("comment_id {} is not a valid uuid", comment_id)))
+}
+
+// the markdown is stored as written, only a body with nothing but
+// whitespace is refused
+fn parse_body(body: String) -> Result<String, AppError> {
+    if body.trim().is_empty() {
+        return Err(AppError::Validation("body cannot be empty".to_string()));
+    }
+
+    if body.chars().count() > MAX_BODY_LENGTH {
+        return Err(AppError::Validation(format!(
+            "body cannot be longer than {} characters",
+            MAX_BODY_LENGTH
+        )));
+    }
+
+    Ok(body)
+}
+
+// authors are not notified of their own comments
+fn parse_mentions(body: &str, user_id: &str) -> Vec<String> {
+    markdown::mentions(body)
+        .into_iter()
+        .filter(|mention| mention != user_id)
+        .collect()
+}
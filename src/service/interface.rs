@@ -1,25 +1,249 @@
-use crate::model::request::TaskRequest;
-use crate::model::task_manager::Task;
-
-use std::error::Error;
+use crate::model::attachment::{Attachment, Download, Upload};
+use crate::model::comment::{CommentDetail, CommentPage};
+use crate::model::error::AppError;
+use crate::model::project::Project;
+use crate::model::request::{
+    BulkRequest, CommentRequest, DependencyRequest, DueQuery, OccurrenceQuery, PageQuery,
+    ProjectDeleteQuery, ProjectRequest, ShareRequest, ShareRoleRequest, TagRequest,
+    TaskDeleteQuery, TaskPatch, TaskQuery, TaskRequest, TaskSearchQuery,
+};
+use crate::model::share::{Share, ShareKind};
+use crate::model::tag::Tag;
+use crate::model::task_event::TaskEventPage;
+use crate::model::task_manager::{
+    TaskDetail, TaskGraph, TaskPage, TaskSearchPage, TaskTree, TaskWriteResult,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
+#[async_trait]
 pub trait TaskServiceInterface {
-    fn insert(
-        &mut self,
+    async fn insert(
+        &self,
         task_request: TaskRequest,
         user_id: String,
-    ) -> Result<Task, Box<dyn Error>>;
-    fn find_all(&mut self, user_id: String) -> Result<Vec<Task>, Box<dyn Error>>;
-    fn find_by_id(
-        &mut self,
-        task_id: String,
+        idempotency_key: Option<String>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn bulk(
+        &self,
+        bulk_request: BulkRequest,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<Vec<TaskWriteResult>, AppError>;
+    async fn find_all(&self, user_id: String, query: TaskQuery) -> Result<TaskPage, AppError>;
+    async fn find_overdue(&self, user_id: String, query: TaskQuery) -> Result<TaskPage, AppError>;
+    async fn search(
+        &self,
+        user_id: String,
+        query: TaskSearchQuery,
+    ) -> Result<TaskSearchPage, AppError>;
+    async fn find_due(
+        &self,
         user_id: String,
-    ) -> Result<Option<Task>, Box<dyn Error>>;
-    fn update(
-        &mut self,
+        due_query: DueQuery,
+        query: TaskQuery,
+    ) -> Result<TaskPage, AppError>;
+    async fn find_by_id(&self, task_id: String, user_id: String) -> Result<TaskDetail, AppError>;
+    async fn update(
+        &self,
         task_request: TaskRequest,
         task_id: String,
         user_id: String,
-    ) -> Result<Task, Box<dyn Error>>;
-    fn delete(&mut self, task_id: String, user_id: String) -> Result<(), Box<dyn Error>>;
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn patch(
+        &self,
+        task_patch: TaskPatch,
+        task_id: String,
+        user_id: String,
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn find_children(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: TaskQuery,
+    ) -> Result<TaskPage, AppError>;
+    async fn find_tree(&self, task_id: String, user_id: String) -> Result<TaskTree, AppError>;
+    async fn find_occurrences(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: OccurrenceQuery,
+    ) -> Result<Vec<DateTime<Utc>>, AppError>;
+    async fn find_dependencies(
+        &self,
+        task_id: String,
+        user_id: String,
+    ) -> Result<TaskGraph, AppError>;
+    async fn add_blocker(
+        &self,
+        dependency_request: DependencyRequest,
+        task_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn remove_blocker(
+        &self,
+        task_id: String,
+        blocker_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn delete(
+        &self,
+        delete_query: TaskDeleteQuery,
+        task_id: String,
+        user_id: String,
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<(), AppError>;
+    async fn find_trash(&self, user_id: String, query: PageQuery) -> Result<TaskPage, AppError>;
+    async fn restore(
+        &self,
+        task_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError>;
+    async fn purge_trash(&self, trashed_before: DateTime<Utc>) -> Result<usize, AppError>;
+    async fn find_history(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<TaskEventPage, AppError>;
+    async fn find_activity(
+        &self,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<TaskEventPage, AppError>;
+}
+
+#[async_trait]
+pub trait TagServiceInterface {
+    async fn insert(&self, tag_request: TagRequest, user_id: String) -> Result<Tag, AppError>;
+    async fn find_all(&self, user_id: String) -> Result<Vec<Tag>, AppError>;
+    async fn update(
+        &self,
+        tag_request: TagRequest,
+        tag_id: String,
+        user_id: String,
+    ) -> Result<Tag, AppError>;
+    async fn delete(&self, tag_id: String, user_id: String) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ProjectServiceInterface {
+    async fn insert(
+        &self,
+        project_request: ProjectRequest,
+        user_id: String,
+    ) -> Result<Project, AppError>;
+    async fn find_all(&self, user_id: String) -> Result<Vec<Project>, AppError>;
+    async fn find_by_id(&self, project_id: String, user_id: String) -> Result<Project, AppError>;
+    async fn update(
+        &self,
+        project_request: ProjectRequest,
+        project_id: String,
+        user_id: String,
+    ) -> Result<Project, AppError>;
+    async fn delete(
+        &self,
+        delete_query: ProjectDeleteQuery,
+        project_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ShareServiceInterface {
+    async fn insert(
+        &self,
+        share_request: ShareRequest,
+        kind: ShareKind,
+        target_id: String,
+        user_id: String,
+    ) -> Result<Share, AppError>;
+    async fn find_all(
+        &self,
+        kind: ShareKind,
+        target_id: String,
+        user_id: String,
+    ) -> Result<Vec<Share>, AppError>;
+    async fn update(
+        &self,
+        share_request: ShareRoleRequest,
+        kind: ShareKind,
+        target_id: String,
+        member: String,
+        user_id: String,
+    ) -> Result<Share, AppError>;
+    async fn delete(
+        &self,
+        kind: ShareKind,
+        target_id: String,
+        member: String,
+        user_id: String,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait CommentServiceInterface {
+    async fn insert(
+        &self,
+        comment_request: CommentRequest,
+        task_id: String,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError>;
+    async fn find_all(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<CommentPage, AppError>;
+    async fn update(
+        &self,
+        comment_request: CommentRequest,
+        task_id: String,
+        comment_id: String,
+        user_id: String,
+    ) -> Result<CommentDetail, AppError>;
+    async fn delete(
+        &self,
+        task_id: String,
+        comment_id: String,
+        user_id: String,
+    ) -> Result<(), AppError>;
+}
+
+// not Send, blobs are streamed by the worker thread that serves the request
+#[async_trait(?Send)]
+pub trait AttachmentServiceInterface {
+    async fn insert(
+        &self,
+        upload: Upload,
+        task_id: String,
+        user_id: String,
+    ) -> Result<Attachment, AppError>;
+    async fn find_all(&self, task_id: String, user_id: String)
+        -> Result<Vec<Attachment>, AppError>;
+    async fn download(
+        &self,
+        task_id: String,
+        attachment_id: String,
+        user_id: String,
+        range: Option<String>,
+        if_range: Option<String>,
+    ) -> Result<Download, AppError>;
+    async fn delete(
+        &self,
+        task_id: String,
+        attachment_id: String,
+        user_id: String,
+    ) -> Result<(), AppError>;
+    async fn delete_orphaned_blobs(&self) -> Result<usize, AppError>;
 }
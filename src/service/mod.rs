@@ -1,2 +1,7 @@
+pub mod attachment;
+pub mod comment;
 pub mod interface;
+pub mod project;
+pub mod share;
+pub mod tag;
 pub mod task_manager;
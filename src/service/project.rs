@@ -0,0 +1,153 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::model::error::AppError;
+use crate::model::project::{Project, ProjectDeleteMode};
+use crate::model::request::{ProjectDeleteQuery, ProjectRequest};
+use crate::model::task_event::AuditContext;
+use crate::repository::interface::ProjectRepositoryInterface;
+use crate::repository::project::ProjectRepository;
+use crate::service::interface::ProjectServiceInterface;
+use async_trait::async_trait;
+
+pub struct ProjectService {
+    repository: ProjectRepository,
+}
+
+impl ProjectService {
+    pub fn new(repository: ProjectRepository) -> Self {
+        ProjectService { repository }
+    }
+}
+
+#[async_trait]
+impl ProjectServiceInterface for ProjectService {
+    async fn insert(
+        &self,
+        project_request: ProjectRequest,
+        user_id: String,
+    ) -> Result<Project, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        if project_request.name.is_empty() {
+            return Err(AppError::Validation("name cannot be empty".to_string()));
+        }
+
+        let now = Utc::now();
+        let project = Project {
+            id: Uuid::new_v4(),
+            owner: user_id,
+            name: project_request.name,
+            description: project_request.description,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let result = self.repository.insert(project).await?;
+        Ok(result)
+    }
+
+    async fn find_all(&self, user_id: String) -> Result<Vec<Project>, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let result = self.repository.find_all(user_id).await?;
+        Ok(result)
+    }
+
+    async fn find_by_id(&self, project_id: String, user_id: String) -> Result<Project, AppError> {
+        // validation
+        let project_id = parse_project_id(&project_id)?;
+
+        let project = self
+            .repository
+            .find_by_id(project_id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("project {} not found", project_id)))?;
+        Ok(project)
+    }
+
+    async fn update(
+        &self,
+        project_request: ProjectRequest,
+        project_id: String,
+        user_id: String,
+    ) -> Result<Project, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let project_id = parse_project_id(&project_id)?;
+
+        if project_request.name.is_empty() {
+            return Err(AppError::Validation("name cannot be empty".to_string()));
+        }
+
+        // find project, the users it is shared with can read it but not change it
+        let mut project = self
+            .repository
+            .find_by_id(project_id, user_id.to_string())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("project {} not found", project_id)))?;
+        if project.owner != user_id {
+            return Err(AppError::Forbidden(format!(
+                "only the owner can change project {}",
+                project_id
+            )));
+        }
+        project.name = project_request.name;
+        project.description = project_request.description;
+        project.updated_at = Utc::now();
+
+        // update project
+        let result = self.repository.update(project).await?;
+        Ok(result)
+    }
+
+    async fn delete(
+        &self,
+        delete_query: ProjectDeleteQuery,
+        project_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<(), AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
+        // validation
+        let project_id = parse_project_id(&project_id)?;
+
+        // tasks are kept in the inbox unless the caller asks to delete them
+        let mode = match delete_query.mode.as_deref() {
+            None | Some("inbox") => ProjectDeleteMode::Inbox,
+            Some("cascade") => ProjectDeleteMode::Cascade,
+            Some(mode) => {
+                return Err(AppError::Validation(format!(
+                    "mode must be inbox or cascade but got {}",
+                    mode
+                )))
+            }
+        };
+
+        self.repository
+            .delete(project_id, user_id, mode, context)
+            .await?;
+        Ok(())
+    }
+}
+
+fn parse_project_id(project_id: &str) -> Result<Uuid, AppError> {
+    if project_id.is_empty() {
+        return Err(AppError::Validation(
+            "project_id cannot be empty".to_string(),
+        ));
+    }
+
+    Uuid::parse_str(project_id)
+        .map_err(|_| AppError::Validation(format!("project_id {} is not a valid uuid", project_id)))
+}
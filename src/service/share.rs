@@ -0,0 +1,157 @@
+use chrono::Utc;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::model::error::AppError;
+use crate::model::request::{ShareRequest, ShareRoleRequest};
+use crate::model::share::{Share, ShareKind, ShareRole, ShareTarget};
+use crate::repository::interface::ShareRepositoryInterface;
+use crate::repository::share::ShareRepository;
+use crate::service::interface::ShareServiceInterface;
+use async_trait::async_trait;
+
+pub struct ShareService {
+    repository: ShareRepository,
+}
+
+impl ShareService {
+    pub fn new(repository: ShareRepository) -> Self {
+        ShareService { repository }
+    }
+}
+
+#[async_trait]
+impl ShareServiceInterface for ShareService {
+    async fn insert(
+        &self,
+        share_request: ShareRequest,
+        kind: ShareKind,
+        target_id: String,
+        user_id: String,
+    ) -> Result<Share, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let target = parse_target(kind, &target_id)?;
+        let member = parse_member(&share_request.user_id)?;
+        if member == user_id {
+            return Err(AppError::Validation(format!(
+                "{} cannot be shared with yourself",
+                target
+            )));
+        }
+        let role = parse_role(&share_request.role)?;
+
+        let now = Utc::now();
+        let (task_id, project_id) = match target {
+            ShareTarget::Task(task_id) => (Some(task_id), None),
+            ShareTarget::Project(project_id) => (None, Some(project_id)),
+        };
+        let share = Share {
+            id: Uuid::new_v4(),
+            user_id: member,
+            task_id,
+            project_id,
+            role,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let result = self.repository.insert(target, share, user_id).await?;
+        Ok(result)
+    }
+
+    async fn find_all(
+        &self,
+        kind: ShareKind,
+        target_id: String,
+        user_id: String,
+    ) -> Result<Vec<Share>, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let target = parse_target(kind, &target_id)?;
+
+        let result = self.repository.find_all(target, user_id).await?;
+        Ok(result)
+    }
+
+    async fn update(
+        &self,
+        share_request: ShareRoleRequest,
+        kind: ShareKind,
+        target_id: String,
+        member: String,
+        user_id: String,
+    ) -> Result<Share, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let target = parse_target(kind, &target_id)?;
+        let member = parse_member(&member)?;
+        let role = parse_role(&share_request.role)?;
+
+        let result = self
+            .repository
+            .update(target, member, role, user_id)
+            .await?;
+        Ok(result)
+    }
+
+    async fn delete(
+        &self,
+        kind: ShareKind,
+        target_id: String,
+        member: String,
+        user_id: String,
+    ) -> Result<(), AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let target = parse_target(kind, &target_id)?;
+        let member = parse_member(&member)?;
+
+        self.repository.delete(target, member, user_id).await?;
+        Ok(())
+    }
+}
+
+fn parse_target(kind: ShareKind, target_id: &str) -> Result<ShareTarget, AppError> {
+    if target_id.is_empty() {
+        return Err(AppError::Validation(format!(
+            "{}_id cannot be empty",
+            kind.as_str()
+        )));
+    }
+
+    Uuid::parse_str(target_id)
+        .map(|target_id| ShareTarget::new(kind, target_id))
+        .map_err(|_| {
+            AppError::Validation(format!(
+                "{}_id {} is not a valid uuid",
+                kind.as_str(),
+                target_id
+            ))
+        })
+}
+
+// user ids come from the token subject, so anything but an empty one is accepted
+fn parse_member(member: &str) -> Result<String, AppError> {
+    if member.is_empty() {
+        return Err(AppError::Validation("user_id cannot be empty".to_string()));
+    }
+
+    Ok(member.to_string())
+}
+
+fn parse_role(role: &str) -> Result<ShareRole, AppError> {
+    ShareRole::from_str(role).map_err(AppError::Validation)
+}
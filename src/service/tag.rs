@@ -0,0 +1,144 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::model::error::AppError;
+use crate::model::request::TagRequest;
+use crate::model::tag::Tag;
+use crate::repository::interface::TagRepositoryInterface;
+use crate::repository::tag::TagRepository;
+use crate::service::interface::TagServiceInterface;
+use async_trait::async_trait;
+
+pub struct TagService {
+    repository: TagRepository,
+}
+
+impl TagService {
+    pub fn new(repository: TagRepository) -> Self {
+        TagService { repository }
+    }
+}
+
+#[async_trait]
+impl TagServiceInterface for TagService {
+    async fn insert(&self, tag_request: TagRequest, user_id: String) -> Result<Tag, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let name = parse_tag_name(&tag_request.name)?;
+
+        let tag = Tag {
+            id: Uuid::new_v4(),
+            owner: user_id,
+            name,
+            created_at: Utc::now(),
+        };
+
+        let tag_name = tag.name.clone();
+        let result = self
+            .repository
+            .insert(tag)
+            .await
+            .map_err(|err| already_exists(err, &tag_name))?;
+        Ok(result)
+    }
+
+    async fn find_all(&self, user_id: String) -> Result<Vec<Tag>, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let result = self.repository.find_all(user_id).await?;
+        Ok(result)
+    }
+
+    async fn update(
+        &self,
+        tag_request: TagRequest,
+        tag_id: String,
+        user_id: String,
+    ) -> Result<Tag, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let tag_id = parse_tag_id(&tag_id)?;
+        let name = parse_tag_name(&tag_request.name)?;
+
+        // find tag
+        let mut tag = self
+            .repository
+            .find_by_id(tag_id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("tag {} not found", tag_id)))?;
+        tag.name = name;
+
+        // update tag
+        let tag_name = tag.name.clone();
+        let result = self
+            .repository
+            .update(tag)
+            .await
+            .map_err(|err| already_exists(err, &tag_name))?;
+        Ok(result)
+    }
+
+    async fn delete(&self, tag_id: String, user_id: String) -> Result<(), AppError> {
+        // validation
+        let tag_id = parse_tag_id(&tag_id)?;
+
+        self.repository.delete(tag_id, user_id).await?;
+        Ok(())
+    }
+}
+
+const MAX_TAG_NAME_LENGTH: usize = 50;
+
+pub fn parse_tag_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("tag name cannot be empty".to_string()));
+    }
+
+    if name.chars().count() > MAX_TAG_NAME_LENGTH {
+        return Err(AppError::Validation(format!(
+            "tag name cannot be longer than {} characters",
+            MAX_TAG_NAME_LENGTH
+        )));
+    }
+
+    Ok(name.to_string())
+}
+
+// validates a list of tag names and drops the duplicates
+pub fn parse_tag_names(names: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut tags: Vec<String> = Vec::with_capacity(names.len());
+    for name in names {
+        let name = parse_tag_name(&name)?;
+        if !tags.contains(&name) {
+            tags.push(name);
+        }
+    }
+
+    Ok(tags)
+}
+
+fn parse_tag_id(tag_id: &str) -> Result<Uuid, AppError> {
+    if tag_id.is_empty() {
+        return Err(AppError::Validation("tag_id cannot be empty".to_string()));
+    }
+
+    Uuid::parse_str(tag_id)
+        .map_err(|_| AppError::Validation(format!("tag_id {} is not a valid uuid", tag_id)))
+}
+
+fn already_exists(err: AppError, name: &str) -> AppError {
+    match err {
+        AppError::Conflict(_) => AppError::Conflict(format!("tag {} already exists", name)),
+        err => err,
+    }
+}
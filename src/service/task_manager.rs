@@ -1,121 +1,1968 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::slice;
+use std::str::FromStr;
 use uuid::Uuid;
 
-use crate::model::request::TaskRequest;
-use crate::model::task_manager::Task;
+use crate::configuration::model::{Idempotency as IdempotencyConfig, Task as TaskConfig};
+use crate::model::error::AppError;
+use crate::model::idempotency::IdempotencyKey;
+use crate::model::request::{
+    BulkOperation, BulkRequest, DependencyRequest, DueQuery, OccurrenceQuery, PageQuery,
+    TaskDeleteQuery, TaskPatch, TaskQuery, TaskRequest, TaskSearchQuery,
+};
+use crate::model::share::ShareRole;
+use crate::model::task_event::{AuditContext, TaskEventPage};
+use crate::model::task_manager::{
+    AssigneeScope, Priority, ProjectScope, SortOrder, SubtaskDeleteMode, TagMatch, Task,
+    TaskDetail, TaskFilter, TaskGraph, TaskPage, TaskSearch, TaskSearchPage, TaskSort, TaskTree,
+    TaskWrite, TaskWriteResult,
+};
 use crate::repository::interface::TaskRepositoryInterface;
 use crate::repository::task_manager::TaskRepository;
 use crate::service::interface::TaskServiceInterface;
-
-use std::error::Error;
+use crate::service::tag::parse_tag_names;
+use crate::util::rrule::RRule;
+use async_trait::async_trait;
+use ring::digest;
+use serde::Serialize;
 
 pub struct TaskService {
     repository: TaskRepository,
+    config: TaskConfig,
+    idempotency: IdempotencyConfig,
 }
 
 impl TaskService {
-    pub fn new(repository: TaskRepository) -> Self {
+    pub fn new(
+        repository: TaskRepository,
+        config: TaskConfig,
+        idempotency: IdempotencyConfig,
+    ) -> Self {
         TaskService {
-            repository: repository,
+            repository,
+            config,
+            idempotency,
         }
     }
-}
 
-impl TaskServiceInterface for TaskService {
-    fn insert(
-        &mut self,
-        task_request: TaskRequest,
-        user_id: String,
-    ) -> Result<Task, Box<dyn Error>> {
-        // validation
-        if user_id.is_empty() {
-            return Err("user_id cannot be empty".into());
+    // the first response for the key, as long as it was for the same request
+    async fn replay(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Option<TaskDetail>, AppError> {
+        let Some(previous) = self
+            .repository
+            .find_idempotency_key(user_id.to_string(), key.to_string())
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if previous.request_hash != request_hash {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Idempotency-Key {} was already used for a different request",
+                key
+            )));
         }
 
+        Ok(Some(serde_json::from_value(previous.response)?))
+    }
+
+    async fn prepare_insert(
+        &self,
+        task_request: TaskRequest,
+        user_id: String,
+    ) -> Result<(Task, Vec<String>), AppError> {
         if task_request.title.is_empty() {
-            return Err("title cannot be empty".into());
+            return Err(AppError::Validation("title cannot be empty".to_string()));
         }
 
         if task_request.description.is_empty() {
-            return Err("description cannot be empty".into());
+            return Err(AppError::Validation(
+                "description cannot be empty".to_string(),
+            ));
+        }
+
+        let due_at = parse_optional_datetime("due_at", task_request.due_at.as_deref())?;
+        let priority = parse_priority(task_request.priority.as_deref())?;
+        let tags = parse_tag_names(task_request.tags.unwrap_or_default())?;
+        let project_id = parse_optional_id("project_id", task_request.project_id.as_deref())?;
+        let parent_id = parse_optional_id("parent_id", task_request.parent_id.as_deref())?;
+        if let Some(parent) = parent_id {
+            self.check_parent(None, parent, &user_id).await?;
         }
+        let recurrence = parse_recurrence(task_request.recurrence.as_deref())?;
+        let assignee_id = parse_assignee(task_request.assignee_id);
 
-        let task = Task {
-            id: Uuid::new_v4().to_string(),
+        let now = Utc::now();
+        let mut task = Task {
+            id: Uuid::new_v4(),
             title: task_request.title,
             description: task_request.description,
             completed: task_request.completed,
             owner: user_id,
+            created_at: now,
+            updated_at: now,
+            completed_at: task_request.completed.then_some(now),
+            due_at,
+            priority,
+            project_id,
+            parent_id,
+            auto_complete: task_request.auto_complete.unwrap_or_default(),
+            recurrence,
+            recurrence_start: None,
+            deleted_at: None,
+            version: 1,
+            assignee_id,
         };
+        schedule_recurrence(&mut task, (None, None))?;
 
-        let result = self.repository.insert(task)?;
-        Ok(result)
+        Ok((task, tags))
+    }
+
+    // the patched task with its next version, and its next occurrence when
+    // the patch completes a recurring task
+    async fn prepare_patch(
+        &self,
+        task_patch: TaskPatch,
+        task_id: Uuid,
+        user_id: &str,
+        if_match: Option<&[i32]>,
+    ) -> Result<(Task, Option<Vec<String>>, Option<Task>), AppError> {
+        // only the members present in the patch are validated
+        let title = match task_patch.title {
+            Some(None) => return Err(AppError::Validation("title cannot be null".to_string())),
+            Some(Some(title)) if title.is_empty() => {
+                return Err(AppError::Validation("title cannot be empty".to_string()))
+            }
+            title => title.flatten(),
+        };
+
+        let description = match task_patch.description {
+            Some(None) => {
+                return Err(AppError::Validation(
+                    "description cannot be null".to_string(),
+                ))
+            }
+            Some(Some(description)) if description.is_empty() => {
+                return Err(AppError::Validation(
+                    "description cannot be empty".to_string(),
+                ))
+            }
+            description => description.flatten(),
+        };
+
+        let completed = match task_patch.completed {
+            Some(None) => return Err(AppError::Validation("completed cannot be null".to_string())),
+            completed => completed.flatten(),
+        };
+
+        let due_at = match task_patch.due_at {
+            Some(due_at) => Some(parse_optional_datetime("due_at", due_at.as_deref())?),
+            None => None,
+        };
+
+        // removing the priority falls back to the default
+        let priority = match task_patch.priority {
+            Some(priority) => Some(parse_priority(priority.as_deref())?),
+            None => None,
+        };
+
+        // removing the tags clears them
+        let tags = match task_patch.tags {
+            Some(tags) => Some(parse_tag_names(tags.unwrap_or_default())?),
+            None => None,
+        };
+
+        // removing the project moves the task to the inbox
+        let project_id = match task_patch.project_id {
+            Some(project_id) => Some(parse_optional_id("project_id", project_id.as_deref())?),
+            None => None,
+        };
+
+        // removing the parent makes it a top level task
+        let parent_id = match task_patch.parent_id {
+            Some(parent_id) => Some(parse_optional_id("parent_id", parent_id.as_deref())?),
+            None => None,
+        };
+
+        let auto_complete = task_patch
+            .auto_complete
+            .map(|auto_complete| auto_complete.unwrap_or_default());
+
+        // removing the recurrence stops the series
+        let recurrence = match task_patch.recurrence {
+            Some(recurrence) => Some(parse_recurrence(recurrence.as_deref())?),
+            None => None,
+        };
+
+        // removing the assignee unassigns the task
+        let assignee_id = task_patch.assignee_id.map(parse_assignee);
+
+        // find task
+        let detail = self
+            .repository
+            .find_by_id(task_id, user_id.to_string())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+        check_role(&detail, ShareRole::Editor)?;
+        let mut task = detail.task;
+        check_version(&task, if_match)?;
+
+        if let Some(Some(parent)) = parent_id.filter(|parent_id| task.parent_id != *parent_id) {
+            self.check_parent(Some(&task), parent, user_id).await?;
+        }
+
+        let now = Utc::now();
+        let previous_schedule = (task.recurrence.clone(), task.due_at);
+        let was_completed = task.completed;
+        if let Some(title) = title {
+            task.title = title;
+        }
+        if let Some(description) = description {
+            task.description = description;
+        }
+        if let Some(due_at) = due_at {
+            task.due_at = due_at;
+        }
+        if let Some(priority) = priority {
+            task.priority = priority;
+        }
+        if let Some(project_id) = project_id {
+            task.project_id = project_id;
+        }
+        if let Some(parent_id) = parent_id {
+            task.parent_id = parent_id;
+        }
+        if let Some(auto_complete) = auto_complete {
+            task.auto_complete = auto_complete;
+        }
+        if let Some(recurrence) = recurrence {
+            task.recurrence = recurrence;
+        }
+        if let Some(assignee_id) = assignee_id {
+            task.assignee_id = assignee_id;
+        }
+        schedule_recurrence(&mut task, previous_schedule)?;
+        if let Some(completed) = completed {
+            self.check_blockers(&task, completed).await?;
+            set_completed(&mut task, completed, now);
+        }
+        task.updated_at = now;
+        task.version += 1;
+
+        let next_occurrence = if task.completed && !was_completed {
+            take_next_occurrence(&mut task, now)
+        } else {
+            None
+        };
+
+        Ok((task, tags, next_occurrence))
     }
 
-    fn find_all(&mut self, user_id: String) -> Result<Vec<Task>, Box<dyn Error>> {
+    async fn prepare_operation(
+        &self,
+        operation: BulkOperation,
+        user_id: &str,
+        written_ids: &mut HashSet<Uuid>,
+    ) -> Result<TaskWrite, AppError> {
+        match operation {
+            BulkOperation::Create { task: task_request } => {
+                let (task, tags) = self
+                    .prepare_insert(task_request, user_id.to_string())
+                    .await?;
+                Ok(TaskWrite::Insert { task, tags })
+            }
+            BulkOperation::Update {
+                id,
+                task: task_patch,
+                version,
+            } => {
+                let task_id = claim_task_id(&id, written_ids)?;
+                let (task, tags, next_occurrence) = self
+                    .prepare_patch(
+                        task_patch,
+                        task_id,
+                        user_id,
+                        version.as_ref().map(slice::from_ref),
+                    )
+                    .await?;
+                Ok(TaskWrite::Update {
+                    task,
+                    tags,
+                    next_occurrence: next_occurrence.map(Box::new),
+                })
+            }
+            BulkOperation::Complete { id, version } => {
+                let task_id = claim_task_id(&id, written_ids)?;
+                let task_patch = TaskPatch {
+                    completed: Some(Some(true)),
+                    ..Default::default()
+                };
+                let (task, tags, next_occurrence) = self
+                    .prepare_patch(
+                        task_patch,
+                        task_id,
+                        user_id,
+                        version.as_ref().map(slice::from_ref),
+                    )
+                    .await?;
+                Ok(TaskWrite::Update {
+                    task,
+                    tags,
+                    next_occurrence: next_occurrence.map(Box::new),
+                })
+            }
+            BulkOperation::Delete {
+                id,
+                children,
+                version,
+            } => {
+                let task_id = claim_task_id(&id, written_ids)?;
+                let mode = self.parse_delete_mode(children.as_deref())?;
+                Ok(TaskWrite::Delete {
+                    task_id,
+                    mode,
+                    expected_versions: version.map(|version| vec![version]),
+                })
+            }
+        }
+    }
+
+    fn parse_delete_mode(&self, children: Option<&str>) -> Result<SubtaskDeleteMode, AppError> {
+        match children {
+            None => Ok(self.config.subtask_delete),
+            Some("cascade") => Ok(SubtaskDeleteMode::Cascade),
+            Some("promote") => Ok(SubtaskDeleteMode::Promote),
+            Some(children) => Err(AppError::Validation(format!(
+                "children must be cascade or promote but got {}",
+                children
+            ))),
+        }
+    }
+
+    // a task can only be placed below an existing task the user can see,
+    // never below itself or one of its own subtasks, and its subtree has to
+    // fit within the configured depth
+    async fn check_parent(
+        &self,
+        task: Option<&Task>,
+        parent: Uuid,
+        user_id: &str,
+    ) -> Result<(), AppError> {
+        if task.map(|task| task.id) == Some(parent) {
+            return Err(AppError::Validation(
+                "a task cannot be its own parent".to_string(),
+            ));
+        }
+
+        let lineage = self
+            .repository
+            .find_lineage(parent, user_id.to_string())
+            .await?;
+        if lineage.is_empty() {
+            return Err(AppError::Validation(format!(
+                "parent_id {} does not exist",
+                parent
+            )));
+        }
+
+        let height = match task {
+            Some(task) => {
+                if lineage.contains(&task.id) {
+                    return Err(AppError::Validation(format!(
+                        "task {} cannot be moved below its own subtask {}",
+                        task.id, parent
+                    )));
+                }
+
+                // read as the owner, the subtasks the user cannot see move too
+                let tree = self
+                    .repository
+                    .find_tree(task.id, task.owner.to_string())
+                    .await?;
+                build_tree(tree).as_ref().map_or(0, tree_height)
+            }
+            None => 0,
+        };
+
+        // the lineage holds the parent and its ancestors, so its length is
+        // the depth the task lands at
+        if lineage.len() + height > self.config.max_depth {
+            return Err(AppError::Validation(format!(
+                "subtasks cannot be nested more than {} levels deep",
+                self.config.max_depth
+            )));
+        }
+
+        Ok(())
+    }
+
+    // a task cannot be completed while any of its blockers is open
+    async fn check_blockers(&self, task: &Task, completed: bool) -> Result<(), AppError> {
+        if !completed || task.completed {
+            return Ok(());
+        }
+
+        let blocker_ids = self
+            .repository
+            .find_open_blockers(task.id, task.owner.to_string())
+            .await?;
+        if blocker_ids.is_empty() {
+            return Ok(());
+        }
+
+        let blocker_ids: Vec<String> = blocker_ids.iter().map(Uuid::to_string).collect();
+        Err(AppError::Conflict(format!(
+            "task {} is blocked by open tasks {}",
+            task.id,
+            blocker_ids.join(", ")
+        )))
+    }
+}
+
+#[async_trait]
+impl TaskServiceInterface for TaskService {
+    async fn insert(
+        &self,
+        task_request: TaskRequest,
+        user_id: String,
+        idempotency_key: Option<String>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
         // validation
         if user_id.is_empty() {
-            return Err("user_id cannot be empty".into());
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
         }
 
-        let tasks = self.repository.find_all(user_id)?;
-        Ok(tasks)
+        // a retry gets the response of the first request instead of a new task
+        let idempotency_key = match idempotency_key {
+            Some(key) => {
+                if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+                    return Err(AppError::Validation(format!(
+                        "Idempotency-Key must be 1 to {} characters",
+                        MAX_IDEMPOTENCY_KEY_LENGTH
+                    )));
+                }
+
+                let request_hash = hash_request(&task_request)?;
+                if let Some(result) = self.replay(&user_id, &key, &request_hash).await? {
+                    return Ok(result);
+                }
+
+                let now = Utc::now();
+                Some(IdempotencyKey {
+                    owner: user_id.to_string(),
+                    key,
+                    request_hash,
+                    response: serde_json::Value::Null,
+                    created_at: now,
+                    expires_at: now + Duration::seconds(self.idempotency.ttl as i64),
+                })
+            }
+            None => None,
+        };
+
+        let (task, tags) = self.prepare_insert(task_request, user_id).await?;
+
+        let retry = idempotency_key.as_ref().map(|idempotency_key| {
+            (
+                idempotency_key.owner.clone(),
+                idempotency_key.key.clone(),
+                idempotency_key.request_hash.clone(),
+            )
+        });
+        match self
+            .repository
+            .insert(task, tags, idempotency_key, context)
+            .await
+        {
+            // a concurrent request with the same key created the task first
+            Err(AppError::Conflict(message)) => match retry {
+                Some((owner, key, request_hash)) => self
+                    .replay(&owner, &key, &request_hash)
+                    .await?
+                    .ok_or(AppError::Conflict(message)),
+                None => Err(AppError::Conflict(message)),
+            },
+            result => result,
+        }
     }
 
-    fn find_by_id(
-        &mut self,
-        task_id: String,
+    async fn bulk(
+        &self,
+        bulk_request: BulkRequest,
         user_id: String,
-    ) -> Result<Option<Task>, Box<dyn Error>> {
+        ref_id: String,
+    ) -> Result<Vec<TaskWriteResult>, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
         // validation
-        if task_id.is_empty() {
-            return Err("task_id cannot be empty".into());
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let atomic = match bulk_request.mode.as_deref() {
+            None | Some("atomic") => true,
+            Some("partial") => false,
+            Some(mode) => {
+                return Err(AppError::Validation(format!(
+                    "mode must be atomic or partial but got {}",
+                    mode
+                )))
+            }
+        };
+
+        if bulk_request.operations.is_empty() || bulk_request.operations.len() > MAX_BULK_OPERATIONS
+        {
+            return Err(AppError::Validation(format!(
+                "operations must have 1 to {} items",
+                MAX_BULK_OPERATIONS
+            )));
         }
 
-        let task = self.repository.find_by_id(task_id, user_id)?;
+        // an operation that cannot be prepared fails on its own, the others
+        // are applied in one transaction
+        let mut results: Vec<TaskWriteResult> = Vec::new();
+        let mut writes = Vec::new();
+        let mut positions = Vec::new();
+        let mut written_ids = HashSet::new();
+        for operation in bulk_request.operations {
+            match self
+                .prepare_operation(operation, &user_id, &mut written_ids)
+                .await
+            {
+                Ok(write) => {
+                    positions.push(results.len());
+                    writes.push(write);
+                    results.push(Ok(None));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+
+        let prepared = results.iter().all(Result::is_ok);
+        if !writes.is_empty() && (prepared || !atomic) {
+            let written = self
+                .repository
+                .bulk(user_id, writes, atomic, context)
+                .await?;
+            for (position, result) in positions.into_iter().zip(written) {
+                results[position] = result;
+            }
+        }
+
+        // in atomic mode one failure leaves every task as it was
+        if atomic {
+            if let Some(failed) = results.iter().position(Result::is_err) {
+                for result in results.iter_mut().filter(|result| result.is_ok()) {
+                    *result = Err(AppError::FailedDependency(format!(
+                        "not applied because operation {} failed",
+                        failed
+                    )));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn find_all(&self, user_id: String, query: TaskQuery) -> Result<TaskPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let filter = parse_filter(query, &user_id)?;
+
+        let page = self.repository.find_all(user_id, filter).await?;
+        Ok(page)
+    }
+
+    async fn find_overdue(&self, user_id: String, query: TaskQuery) -> Result<TaskPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        // overdue means due in the past and still open
+        let mut filter = parse_filter(query, &user_id)?;
+        filter.overdue = true;
+        filter.completed = Some(false);
+        filter.due_before = Some(Utc::now());
+
+        let page = self.repository.find_all(user_id, filter).await?;
+        Ok(page)
+    }
+
+    async fn search(
+        &self,
+        user_id: String,
+        query: TaskSearchQuery,
+    ) -> Result<TaskSearchPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let terms = parse_search_terms(query.q.as_deref())?;
+        let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+        let search = TaskSearch {
+            terms,
+            completed: query.completed,
+            offset,
+            limit,
+        };
+
+        let page = self.repository.search(user_id, search).await?;
+        Ok(page)
+    }
+
+    async fn find_due(
+        &self,
+        user_id: String,
+        due_query: DueQuery,
+        query: TaskQuery,
+    ) -> Result<TaskPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let due_before = parse_optional_datetime("before", due_query.before.as_deref())?;
+        let due_after = parse_optional_datetime("after", due_query.after.as_deref())?;
+
+        if due_before.is_none() && due_after.is_none() {
+            return Err(AppError::Validation(
+                "before or after must be provided".to_string(),
+            ));
+        }
+
+        if let (Some(before), Some(after)) = (due_before, due_after) {
+            if after > before {
+                return Err(AppError::Validation(
+                    "after cannot be later than before".to_string(),
+                ));
+            }
+        }
+
+        let mut filter = parse_filter(query, &user_id)?;
+        filter.due_before = due_before;
+        filter.due_after = due_after;
+
+        let page = self.repository.find_all(user_id, filter).await?;
+        Ok(page)
+    }
+
+    async fn find_by_id(&self, task_id: String, user_id: String) -> Result<TaskDetail, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        let task = self
+            .repository
+            .find_by_id(task_id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
         Ok(task)
     }
 
-    fn update(
-        &mut self,
+    async fn update(
+        &self,
         task_request: TaskRequest,
         task_id: String,
         user_id: String,
-    ) -> Result<Task, Box<dyn Error>> {
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
         // validation
         if user_id.is_empty() {
-            return Err("user_id cannot be empty".into());
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
         }
 
-        if task_id.is_empty() {
-            return Err("task_id cannot be empty".into());
-        }
+        let task_id = parse_task_id(&task_id)?;
 
         if task_request.title.is_empty() {
-            return Err("title cannot be empty".into());
+            return Err(AppError::Validation("title cannot be empty".to_string()));
         }
 
         if task_request.description.is_empty() {
-            return Err("description cannot be empty".into());
+            return Err(AppError::Validation(
+                "description cannot be empty".to_string(),
+            ));
         }
 
+        let due_at = parse_optional_datetime("due_at", task_request.due_at.as_deref())?;
+        let priority = parse_priority(task_request.priority.as_deref())?;
+        let tags = parse_tag_names(task_request.tags.unwrap_or_default())?;
+        let project_id = parse_optional_id("project_id", task_request.project_id.as_deref())?;
+        let parent_id = parse_optional_id("parent_id", task_request.parent_id.as_deref())?;
+        let recurrence = parse_recurrence(task_request.recurrence.as_deref())?;
+        let assignee_id = parse_assignee(task_request.assignee_id);
+
         // find task
-        let mut task = self.repository.find_by_id(task_id, user_id)?.unwrap();
+        let detail = self
+            .repository
+            .find_by_id(task_id, user_id.to_string())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+        check_role(&detail, ShareRole::Editor)?;
+        let mut task = detail.task;
+        check_version(&task, if_match.as_deref())?;
+
+        if let Some(parent) = parent_id.filter(|parent| task.parent_id != Some(*parent)) {
+            self.check_parent(Some(&task), parent, &user_id).await?;
+        }
+
+        let now = Utc::now();
+        let previous_schedule = (task.recurrence.clone(), task.due_at);
+        let was_completed = task.completed;
         task.title = task_request.title;
         task.description = task_request.description;
-        task.completed = task_request.completed;
+        task.due_at = due_at;
+        task.priority = priority;
+        task.project_id = project_id;
+        task.parent_id = parent_id;
+        task.auto_complete = task_request.auto_complete.unwrap_or_default();
+        task.recurrence = recurrence;
+        task.assignee_id = assignee_id;
+        task.updated_at = now;
+        task.version += 1;
+        schedule_recurrence(&mut task, previous_schedule)?;
+        self.check_blockers(&task, task_request.completed).await?;
+        set_completed(&mut task, task_request.completed, now);
+
+        let next_occurrence = if task.completed && !was_completed {
+            take_next_occurrence(&mut task, now)
+        } else {
+            None
+        };
 
         // update task
-        let result = self.repository.update(task)?;
+        let result = self
+            .repository
+            .update(task, user_id, Some(tags), next_occurrence, context)
+            .await?;
         Ok(result)
     }
 
-    fn delete(&mut self, task_id: String, user_id: String) -> Result<(), Box<dyn Error>> {
+    async fn patch(
+        &self,
+        task_patch: TaskPatch,
+        task_id: String,
+        user_id: String,
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
+        // validation, only for the members present in the patch
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+
+        let (task, tags, next_occurrence) = self
+            .prepare_patch(task_patch, task_id, &user_id, if_match.as_deref())
+            .await?;
+
+        // update task
+        let result = self
+            .repository
+            .update(task, user_id, tags, next_occurrence, context)
+            .await?;
+        Ok(result)
+    }
+
+    async fn find_children(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: TaskQuery,
+    ) -> Result<TaskPage, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+        let mut filter = parse_filter(query, &user_id)?;
+
+        // an unknown task is a 404 rather than an empty list
+        self.repository
+            .find_by_id(task_id, user_id.to_string())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+
+        filter.parent_id = Some(task_id);
+
+        let page = self.repository.find_all(user_id, filter).await?;
+        Ok(page)
+    }
+
+    async fn find_dependencies(
+        &self,
+        task_id: String,
+        user_id: String,
+    ) -> Result<TaskGraph, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        self.repository
+            .find_by_id(task_id, user_id.to_string())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+
+        let dependencies = self
+            .repository
+            .find_dependencies(task_id, user_id.to_string(), false)
+            .await?;
+
+        let mut task_ids = vec![task_id];
+        for dependency in &dependencies {
+            if !task_ids.contains(&dependency.blocker_id) {
+                task_ids.push(dependency.blocker_id);
+            }
+        }
+        let tasks = self.repository.find_by_ids(task_ids, user_id).await?;
+
+        Ok(TaskGraph {
+            tasks,
+            dependencies,
+        })
+    }
+
+    async fn add_blocker(
+        &self,
+        dependency_request: DependencyRequest,
+        task_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let task_id = parse_task_id(&task_id)?;
+        let blocker_id = parse_optional_id("blocker_id", Some(&dependency_request.blocker_id))?
+            .ok_or_else(|| AppError::Validation("blocker_id cannot be empty".to_string()))?;
+
+        if blocker_id == task_id {
+            return Err(AppError::Validation(
+                "a task cannot block itself".to_string(),
+            ));
+        }
+
+        // the new edge closes a cycle when the task already blocks the
+        // blocker, directly or through other tasks. Trashed tasks are walked
+        // too, restoring them must not bring back a cycle
+        let upstream = self
+            .repository
+            .find_dependencies(blocker_id, user_id.to_string(), true)
+            .await?;
+        if upstream
+            .iter()
+            .any(|dependency| dependency.blocker_id == task_id)
+        {
+            return Err(AppError::Validation(format!(
+                "task {} already depends on task {}, blocking it would create a cycle",
+                blocker_id, task_id
+            )));
+        }
+
+        let result = self
+            .repository
+            .add_dependency(task_id, blocker_id, user_id, context)
+            .await?;
+        Ok(result)
+    }
+
+    async fn remove_blocker(
+        &self,
+        task_id: String,
+        blocker_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
         // validation
-        if task_id.is_empty() {
-            return Err("task_id cannot be empty".into());
+        let task_id = parse_task_id(&task_id)?;
+        let blocker_id = parse_optional_id("blocker_id", Some(&blocker_id))?
+            .ok_or_else(|| AppError::Validation("blocker_id cannot be empty".to_string()))?;
+
+        let result = self
+            .repository
+            .remove_dependency(task_id, blocker_id, user_id, context)
+            .await?;
+        Ok(result)
+    }
+
+    async fn find_occurrences(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: OccurrenceQuery,
+    ) -> Result<Vec<DateTime<Utc>>, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE as usize);
+        if !(1..=MAX_PAGE_SIZE as usize).contains(&limit) {
+            return Err(AppError::Validation(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
         }
 
-        self.repository.delete(task_id, user_id)?;
+        let TaskDetail { task, .. } = self
+            .repository
+            .find_by_id(task_id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+
+        // a task that does not repeat has no upcoming occurrences
+        let (Some(recurrence), Some(due_at)) = (task.recurrence.as_deref(), task.due_at) else {
+            return Ok(Vec::new());
+        };
+        let rule = RRule::from_str(recurrence).map_err(AppError::Validation)?;
+
+        Ok(rule
+            .occurrences(task.recurrence_start.unwrap_or(due_at))
+            .filter(|occurrence| *occurrence > due_at)
+            .take(limit)
+            .collect())
+    }
+
+    async fn find_tree(&self, task_id: String, user_id: String) -> Result<TaskTree, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        let tasks = self.repository.find_tree(task_id, user_id).await?;
+        let tree = build_tree(tasks)
+            .ok_or_else(|| AppError::NotFound(format!("task {} not found", task_id)))?;
+        Ok(tree)
+    }
+
+    async fn delete(
+        &self,
+        delete_query: TaskDeleteQuery,
+        task_id: String,
+        user_id: String,
+        if_match: Option<Vec<i32>>,
+        ref_id: String,
+    ) -> Result<(), AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        let mode = self.parse_delete_mode(delete_query.children.as_deref())?;
+
+        self.repository
+            .delete(task_id, user_id, mode, if_match, context)
+            .await?;
         Ok(())
     }
+
+    async fn find_trash(&self, user_id: String, query: PageQuery) -> Result<TaskPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+        let page = self.repository.find_trash(user_id, offset, limit).await?;
+        Ok(page)
+    }
+
+    async fn restore(
+        &self,
+        task_id: String,
+        user_id: String,
+        ref_id: String,
+    ) -> Result<TaskDetail, AppError> {
+        let context = AuditContext::new(&user_id, ref_id);
+
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+
+        let result = self.repository.restore(task_id, user_id, context).await?;
+        Ok(result)
+    }
+
+    async fn purge_trash(&self, trashed_before: DateTime<Utc>) -> Result<usize, AppError> {
+        let purged = self.repository.purge(trashed_before).await?;
+        Ok(purged)
+    }
+
+    async fn find_history(
+        &self,
+        task_id: String,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<TaskEventPage, AppError> {
+        // validation
+        let task_id = parse_task_id(&task_id)?;
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+        // history outlives the task, so trashed and purged tasks still have one
+        let page = self
+            .repository
+            .find_events(user_id, Some(task_id), offset, limit)
+            .await?;
+        if page.total == 0 {
+            return Err(AppError::NotFound(format!("task {} not found", task_id)));
+        }
+
+        Ok(page)
+    }
+
+    async fn find_activity(
+        &self,
+        user_id: String,
+        query: PageQuery,
+    ) -> Result<TaskEventPage, AppError> {
+        // validation
+        if user_id.is_empty() {
+            return Err(AppError::Validation("user_id cannot be empty".to_string()));
+        }
+
+        let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+        let page = self
+            .repository
+            .find_events(user_id, None, offset, limit)
+            .await?;
+        Ok(page)
+    }
+}
+
+// completed_at follows the completed flag, keeping the original
+// completion time when an already completed task is saved again
+fn set_completed(task: &mut Task, completed: bool, now: DateTime<Utc>) {
+    task.completed_at = match (completed, task.completed_at) {
+        (true, Some(completed_at)) => Some(completed_at),
+        (true, None) => Some(now),
+        (false, _) => None,
+    };
+    task.completed = completed;
+}
+
+fn parse_recurrence(value: Option<&str>) -> Result<Option<String>, AppError> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => RRule::from_str(value)
+            .map(|_| Some(value.to_string()))
+            .map_err(|err| AppError::Validation(format!("recurrence is invalid: {}", err))),
+    }
+}
+
+// a recurring task needs a due date to repeat from, and its series starts
+// over from the due date whenever the rule or the due date is changed
+fn schedule_recurrence(
+    task: &mut Task,
+    previous: (Option<String>, Option<DateTime<Utc>>),
+) -> Result<(), AppError> {
+    if task.recurrence.is_some() && task.due_at.is_none() {
+        return Err(AppError::Validation(
+            "recurrence requires due_at".to_string(),
+        ));
+    }
+
+    if (task.recurrence.clone(), task.due_at) != previous {
+        task.recurrence_start = task.recurrence.as_ref().and(task.due_at);
+    }
+
+    Ok(())
+}
+
+// the rule moves from the completed task to its next occurrence, so
+// reopening and completing the task again does not repeat it twice
+fn take_next_occurrence(task: &mut Task, now: DateTime<Utc>) -> Option<Task> {
+    let due_at = task.due_at?;
+    let recurrence = task.recurrence.take()?;
+    let recurrence_start = task.recurrence_start.take().unwrap_or(due_at);
+
+    let rule = RRule::from_str(&recurrence).ok()?;
+    let next_due_at = rule.next_after(recurrence_start, due_at)?;
+
+    Some(Task {
+        id: Uuid::new_v4(),
+        title: task.title.clone(),
+        description: task.description.clone(),
+        completed: false,
+        owner: task.owner.clone(),
+        created_at: now,
+        updated_at: now,
+        completed_at: None,
+        due_at: Some(next_due_at),
+        priority: task.priority,
+        project_id: task.project_id,
+        parent_id: task.parent_id,
+        auto_complete: task.auto_complete,
+        recurrence: Some(recurrence),
+        recurrence_start: Some(recurrence_start),
+        deleted_at: None,
+        version: 1,
+        assignee_id: task.assignee_id.clone(),
+    })
+}
+
+// the repository checks the role again when writing, this only saves the
+// validation that would come before it
+fn check_role(detail: &TaskDetail, required: ShareRole) -> Result<(), AppError> {
+    if detail.role < required {
+        return Err(AppError::Forbidden(format!(
+            "task {} is shared with you as {}",
+            detail.task.id,
+            detail.role.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+// If-Match lists the versions the client expects, anything else means the
+// task was changed since the client read it
+fn check_version(task: &Task, if_match: Option<&[i32]>) -> Result<(), AppError> {
+    match if_match {
+        Some(versions) if !versions.contains(&task.version) => Err(AppError::PreconditionFailed(
+            format!("task {} was changed by another request", task.id),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// the request as parsed, so whitespace and member order make no difference
+fn hash_request<T: Serialize>(request: &T) -> Result<String, AppError> {
+    let body = serde_json::to_vec(request)?;
+    let hash = digest::digest(&digest::SHA256, &body);
+    Ok(hash
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+// every operation of a batch is prepared against the tasks as they were
+// before it, so a batch can only write a task once
+fn claim_task_id(task_id: &str, written_ids: &mut HashSet<Uuid>) -> Result<Uuid, AppError> {
+    let task_id = parse_task_id(task_id)?;
+    if !written_ids.insert(task_id) {
+        return Err(AppError::Validation(format!(
+            "task {} is written by more than one operation",
+            task_id
+        )));
+    }
+
+    Ok(task_id)
+}
+
+pub fn parse_task_id(task_id: &str) -> Result<Uuid, AppError> {
+    if task_id.is_empty() {
+        return Err(AppError::Validation("task_id cannot be empty".to_string()));
+    }
+
+    Uuid::parse_str(task_id)
+        .map_err(|_| AppError::Validation(format!("task_id {} is not a valid uuid", task_id)))
+}
+
+fn parse_optional_datetime(
+    field: &str,
+    value: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    match value {
+        None | Some("") => Ok(None),
+        Some(value) => DateTime::parse_from_rfc3339(value)
+            .map(|datetime| Some(datetime.with_timezone(&Utc)))
+            .map_err(|err| {
+                AppError::Validation(format!("{} must be an RFC 3339 datetime: {}", field, err))
+            }),
+    }
+}
+
+fn parse_optional_id(field: &str, value: Option<&str>) -> Result<Option<Uuid>, AppError> {
+    match value {
+        None | Some("") => Ok(None),
+        Some(value) => Uuid::parse_str(value)
+            .map(Some)
+            .map_err(|_| AppError::Validation(format!("{} {} is not a valid uuid", field, value))),
+    }
+}
+
+// the tasks come root first, as returned by the repository
+fn build_tree(mut tasks: Vec<TaskDetail>) -> Option<TaskTree> {
+    if tasks.is_empty() {
+        return None;
+    }
+
+    let root = tasks.remove(0);
+    let mut children: HashMap<Uuid, Vec<TaskDetail>> = HashMap::new();
+    for task in tasks {
+        if let Some(parent_id) = task.task.parent_id {
+            children.entry(parent_id).or_default().push(task);
+        }
+    }
+
+    Some(attach_children(root, &mut children))
+}
+
+fn attach_children(task: TaskDetail, children: &mut HashMap<Uuid, Vec<TaskDetail>>) -> TaskTree {
+    let nested = children
+        .remove(&task.task.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| attach_children(child, children))
+        .collect();
+
+    TaskTree {
+        task,
+        children: nested,
+    }
+}
+
+fn tree_height(tree: &TaskTree) -> usize {
+    tree.children
+        .iter()
+        .map(|child| tree_height(child) + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+// the assignee is checked against the users the task is shared with when
+// it is written, an empty one leaves the task unassigned
+fn parse_assignee(value: Option<String>) -> Option<String> {
+    value.filter(|assignee_id| !assignee_id.is_empty())
+}
+
+fn parse_priority(value: Option<&str>) -> Result<Priority, AppError> {
+    match value {
+        None => Ok(Priority::default()),
+        Some(value) => Priority::from_str(value).map_err(AppError::Validation),
+    }
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 10;
+const MAX_PAGE_SIZE: i64 = 100;
+const MAX_SEARCH_LENGTH: usize = 200;
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+const MAX_BULK_OPERATIONS: usize = 100;
+const SORT_FIELDS: &str = "id, title, created_at, updated_at, completed_at, due_at, priority";
+
+// returns the offset and limit of the requested page
+pub fn parse_page(limit: Option<i64>, cursor: Option<&str>) -> Result<(i64, i64), AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+
+    // the cursor is the opaque offset returned as next_cursor
+    let offset = match cursor {
+        None | Some("") => 0,
+        Some(cursor) => match cursor.parse::<i64>() {
+            Ok(offset) if offset >= 0 => offset,
+            _ => return Err(AppError::Validation("cursor is invalid".to_string())),
+        },
+    };
+
+    Ok((offset, limit))
+}
+
+fn parse_search_terms(q: Option<&str>) -> Result<Vec<String>, AppError> {
+    let q = q.unwrap_or_default();
+    if q.chars().count() > MAX_SEARCH_LENGTH {
+        return Err(AppError::Validation(format!(
+            "q cannot be longer than {} characters",
+            MAX_SEARCH_LENGTH
+        )));
+    }
+
+    // anything but letters and digits separates words, so tsquery operators
+    // in q are never passed on
+    let terms: Vec<String> = q
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    if terms.is_empty() {
+        return Err(AppError::Validation(
+            "q must contain at least one word".to_string(),
+        ));
+    }
+
+    Ok(terms)
+}
+
+fn parse_filter(query: TaskQuery, user_id: &str) -> Result<TaskFilter, AppError> {
+    let (offset, limit) = parse_page(query.limit, query.cursor.as_deref())?;
+
+    let sort = match query.sort.as_deref() {
+        None | Some("id") => TaskSort::Id,
+        Some("title") => TaskSort::Title,
+        Some("created_at") => TaskSort::CreatedAt,
+        Some("updated_at") => TaskSort::UpdatedAt,
+        Some("completed_at") => TaskSort::CompletedAt,
+        Some("due_at") => TaskSort::DueAt,
+        Some("priority") => TaskSort::Priority,
+        Some(sort) => {
+            return Err(AppError::Validation(format!(
+                "sort must be one of {} but got {}",
+                SORT_FIELDS, sort
+            )))
+        }
+    };
+
+    let order = match query.order.as_deref() {
+        None | Some("asc") => SortOrder::Asc,
+        Some("desc") => SortOrder::Desc,
+        Some(order) => {
+            return Err(AppError::Validation(format!(
+                "order must be asc or desc but got {}",
+                order
+            )))
+        }
+    };
+
+    let title = query.title.filter(|title| !title.is_empty());
+
+    let project = match query.project_id.as_deref() {
+        None | Some("") => None,
+        Some("inbox") => Some(ProjectScope::Inbox),
+        Some(project_id) => {
+            parse_optional_id("project_id", Some(project_id))?.map(ProjectScope::Project)
+        }
+    };
+
+    // me stands for the user listing the tasks
+    let assignee = match query.assignee.as_deref() {
+        None | Some("") => None,
+        Some("none") => Some(AssigneeScope::Unassigned),
+        Some("me") => Some(AssigneeScope::User(user_id.to_string())),
+        Some(assignee) => Some(AssigneeScope::User(assignee.to_string())),
+    };
+
+    let tags = parse_tag_names(query.tag)?;
+    let tag_match = match query.tag_match.as_deref() {
+        None | Some("any") => TagMatch::Any,
+        Some("all") => TagMatch::All,
+        Some(tag_match) => {
+            return Err(AppError::Validation(format!(
+                "tag_match must be any or all but got {}",
+                tag_match
+            )))
+        }
+    };
+
+    Ok(TaskFilter {
+        completed: query.completed,
+        title,
+        due_after: None,
+        due_before: None,
+        overdue: false,
+        project,
+        parent_id: None,
+        assignee,
+        tags,
+        tag_match,
+        sort,
+        order,
+        offset,
+        limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::{cache, database};
+    use crate::event::bus::EventBus;
+    use crate::model::request::{ProjectRequest, ShareRequest, TagRequest};
+    use crate::model::share::ShareKind;
+    use crate::repository::project::ProjectRepository;
+    use crate::repository::share::ShareRepository;
+    use crate::repository::tag::TagRepository;
+    use crate::service::interface::{
+        ProjectServiceInterface, ShareServiceInterface, TagServiceInterface,
+    };
+    use crate::service::project::ProjectService;
+    use crate::service::share::ShareService;
+    use crate::service::tag::TagService;
+    use actix_web::web::Query;
+    use futures_util::future;
+
+    fn query(query_string: &str) -> TaskQuery {
+        Query::<TaskQuery>::from_query(query_string)
+            .unwrap()
+            .into_inner()
+    }
+
+    fn is_validation<T>(result: Result<T, AppError>) -> bool {
+        matches!(result, Err(AppError::Validation(_)))
+    }
+
+    #[test]
+    fn page_limit_is_bounded() {
+        assert_eq!(parse_page(None, None).unwrap(), (0, DEFAULT_PAGE_SIZE));
+        assert_eq!(parse_page(Some(1), None).unwrap(), (0, 1));
+        assert_eq!(parse_page(Some(100), None).unwrap(), (0, 100));
+        for limit in [0, 101, -1, i64::MAX] {
+            assert!(is_validation(parse_page(Some(limit), None)), "{}", limit);
+        }
+        // a limit that is not a number never reaches the service
+        assert!(Query::<TaskQuery>::from_query("limit=ten").is_err());
+    }
+
+    #[test]
+    fn cursor_is_a_non_negative_offset() {
+        assert_eq!(parse_page(None, Some("")).unwrap(), (0, DEFAULT_PAGE_SIZE));
+        assert_eq!(parse_page(Some(5), Some("20")).unwrap(), (20, 5));
+        for cursor in ["-1", "abc", "1.5", " 1", "99999999999999999999", "MTA="] {
+            assert!(is_validation(parse_page(None, Some(cursor))), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn filter_parses_sort_order_and_scopes() {
+        let filter = parse_filter(query(""), "alice").unwrap();
+        assert!(matches!(filter.sort, TaskSort::Id));
+        assert!(matches!(filter.order, SortOrder::Asc));
+        assert!(matches!(filter.tag_match, TagMatch::Any));
+        assert_eq!((filter.offset, filter.limit), (0, DEFAULT_PAGE_SIZE));
+
+        let filter = parse_filter(
+            query("sort=due_at&order=desc&tag_match=all&assignee=me&project_id=inbox&cursor=30&limit=15"),
+            "alice",
+        )
+        .unwrap();
+        assert!(matches!(filter.sort, TaskSort::DueAt));
+        assert!(matches!(filter.order, SortOrder::Desc));
+        assert!(matches!(filter.tag_match, TagMatch::All));
+        assert!(matches!(filter.assignee, Some(AssigneeScope::User(user)) if user == "alice"));
+        assert!(matches!(filter.project, Some(ProjectScope::Inbox)));
+        assert_eq!((filter.offset, filter.limit), (30, 15));
+    }
+
+    #[test]
+    fn filter_rejects_unknown_values() {
+        for query_string in [
+            "sort=owner",
+            "sort=title;drop",
+            "order=up",
+            "tag_match=none",
+            "project_id=not-a-uuid",
+            "cursor=x",
+            "limit=0",
+        ] {
+            assert!(
+                is_validation(parse_filter(query(query_string), "alice")),
+                "{}",
+                query_string
+            );
+        }
+    }
+
+    fn terms(q: &str) -> Vec<String> {
+        parse_search_terms(Some(q)).unwrap()
+    }
+
+    #[test]
+    fn search_terms_are_lowercased_words() {
+        assert_eq!(terms("Write REPORT"), ["write", "report"]);
+        assert_eq!(terms("  spaced   out\t"), ["spaced", "out"]);
+        assert_eq!(terms("naïve Café 2024"), ["naïve", "café", "2024"]);
+    }
+
+    #[test]
+    fn punctuation_and_tsquery_operators_separate_words() {
+        assert_eq!(terms("report, draft. final!"), ["report", "draft", "final"]);
+        assert_eq!(terms("fix&deploy|ship"), ["fix", "deploy", "ship"]);
+        assert_eq!(terms("!urgent"), ["urgent"]);
+        assert_eq!(terms("title:report*"), ["title", "report"]);
+        assert_eq!(terms("(a|b)&!c"), ["a", "b", "c"]);
+        assert_eq!(terms("o'brien"), ["o", "brien"]);
+    }
+
+    #[test]
+    fn search_needs_at_least_one_word() {
+        for q in [
+            None,
+            Some(""),
+            Some("   "),
+            Some("\t\n"),
+            Some("&|!:*()"),
+            Some("--"),
+        ] {
+            assert!(is_validation(parse_search_terms(q)), "{:?}", q);
+        }
+    }
+
+    #[test]
+    fn search_length_is_counted_in_characters() {
+        let longest = "é".repeat(MAX_SEARCH_LENGTH);
+        assert_eq!(terms(&longest), [longest.as_str()]);
+        assert!(is_validation(parse_search_terms(Some(&format!(
+            "{}é",
+            longest
+        )))));
+    }
+
+    #[test]
+    fn request_hash_follows_the_body() {
+        let hash = hash_request(&request("report")).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash, hash_request(&request("report")).unwrap());
+        assert_ne!(hash, hash_request(&request("Report")).unwrap());
+        let due = TaskRequest {
+            due_at: Some("2030-01-01T00:00:00Z".to_string()),
+            ..request("report")
+        };
+        assert_ne!(hash, hash_request(&due).unwrap());
+    }
+
+    async fn connect() -> (TaskService, ProjectService) {
+        let (db_pool, redis_client) = (database(), cache().await);
+        let repository =
+            TaskRepository::new(db_pool.clone(), redis_client.clone(), EventBus::new());
+        let task_config = TaskConfig {
+            max_depth: 5,
+            ..TaskConfig::default()
+        };
+        let service = TaskService::new(repository, task_config, IdempotencyConfig { ttl: 60 });
+        let projects = ProjectService::new(ProjectRepository::new(db_pool, redis_client));
+        (service, projects)
+    }
+
+    fn request(task_title: &str) -> TaskRequest {
+        TaskRequest {
+            title: task_title.to_string(),
+            description: "patch test".to_string(),
+            completed: false,
+            due_at: None,
+            priority: None,
+            tags: None,
+            project_id: None,
+            parent_id: None,
+            auto_complete: None,
+            recurrence: None,
+            assignee_id: None,
+        }
+    }
+
+    fn patch(body: &str) -> TaskPatch {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn patch_clears_null_members_and_keeps_absent_ones() {
+        let (service, projects) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let project = projects
+            .insert(
+                ProjectRequest {
+                    name: "patch".to_string(),
+                    description: None,
+                },
+                user_id.clone(),
+            )
+            .await
+            .unwrap();
+        let parent = service
+            .insert(request("parent"), user_id.clone(), None, "test".to_string())
+            .await
+            .unwrap();
+        let child = service
+            .insert(
+                TaskRequest {
+                    due_at: Some("2030-01-01T00:00:00Z".to_string()),
+                    project_id: Some(project.id.to_string()),
+                    parent_id: Some(parent.task.id.to_string()),
+                    ..request("child")
+                },
+                user_id.clone(),
+                None,
+                "test".to_string(),
+            )
+            .await
+            .unwrap();
+        let task_id = child.task.id.to_string();
+
+        let renamed = service
+            .patch(
+                patch(r#"{"title":"renamed"}"#),
+                task_id.clone(),
+                user_id.clone(),
+                None,
+                "test".to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(renamed.task.title, "renamed");
+        assert_eq!(renamed.task.due_at, child.task.due_at);
+        assert_eq!(renamed.task.project_id, Some(project.id));
+        assert_eq!(renamed.task.parent_id, Some(parent.task.id));
+
+        let cleared = service
+            .patch(
+                patch(r#"{"due_at":null,"project_id":null,"parent_id":null}"#),
+                task_id,
+                user_id,
+                None,
+                "test".to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cleared.task.title, "renamed");
+        assert_eq!(cleared.task.due_at, None);
+        assert_eq!(cleared.task.project_id, None);
+        assert_eq!(cleared.task.parent_id, None);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn subtasks_of_a_shared_parent_need_an_editor() {
+        let (service, _) = connect().await;
+        let shares = ShareService::new(ShareRepository::new(database()));
+        let [owner, editor, viewer, stranger] = [(); 4].map(|_| Uuid::new_v4().to_string());
+        let parent = service
+            .insert(request("shared"), owner.clone(), None, "test".to_string())
+            .await
+            .unwrap();
+        for (user_id, role) in [(&editor, "editor"), (&viewer, "viewer")] {
+            shares
+                .insert(
+                    ShareRequest {
+                        user_id: user_id.clone(),
+                        role: role.to_string(),
+                    },
+                    ShareKind::Task,
+                    parent.task.id.to_string(),
+                    owner.clone(),
+                )
+                .await
+                .unwrap();
+        }
+        let subtask = |user_id: &String| {
+            service.insert(
+                TaskRequest {
+                    parent_id: Some(parent.task.id.to_string()),
+                    ..request("subtask")
+                },
+                user_id.clone(),
+                None,
+                "test".to_string(),
+            )
+        };
+
+        let child = subtask(&editor).await.unwrap();
+        assert_eq!(child.task.parent_id, Some(parent.task.id));
+        assert!(matches!(
+            subtask(&viewer).await,
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(is_validation(subtask(&stranger).await));
+    }
+
+    async fn create(service: &TaskService, user_id: &str, parent: Option<Uuid>) -> Uuid {
+        let task_request = TaskRequest {
+            parent_id: parent.map(|parent| parent.to_string()),
+            ..request("hierarchy")
+        };
+        service
+            .insert(task_request, user_id.to_string(), None, "test".to_string())
+            .await
+            .unwrap()
+            .task
+            .id
+    }
+
+    async fn block(
+        service: &TaskService,
+        user_id: &str,
+        task_id: Uuid,
+        blocker_id: Uuid,
+    ) -> Result<TaskDetail, AppError> {
+        service
+            .add_blocker(
+                DependencyRequest {
+                    blocker_id: blocker_id.to_string(),
+                },
+                task_id.to_string(),
+                user_id.to_string(),
+                "test".to_string(),
+            )
+            .await
+    }
+
+    async fn apply(
+        service: &TaskService,
+        user_id: &str,
+        task_id: Uuid,
+        task_patch: TaskPatch,
+    ) -> Result<TaskDetail, AppError> {
+        service
+            .patch(
+                task_patch,
+                task_id.to_string(),
+                user_id.to_string(),
+                None,
+                "test".to_string(),
+            )
+            .await
+    }
+
+    fn move_below(parent: Uuid) -> TaskPatch {
+        TaskPatch {
+            parent_id: Some(Some(parent.to_string())),
+            ..TaskPatch::default()
+        }
+    }
+
+    fn complete() -> TaskPatch {
+        TaskPatch {
+            completed: Some(Some(true)),
+            ..TaskPatch::default()
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn a_task_cannot_be_moved_below_itself() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let root = create(&service, &user_id, None).await;
+        let middle = create(&service, &user_id, Some(root)).await;
+        let leaf = create(&service, &user_id, Some(middle)).await;
+
+        assert!(is_validation(
+            apply(&service, &user_id, root, move_below(root)).await
+        ));
+        assert!(is_validation(
+            apply(&service, &user_id, root, move_below(middle)).await
+        ));
+        assert!(is_validation(
+            apply(&service, &user_id, root, move_below(leaf)).await
+        ));
+        // a move that closes no cycle is still allowed
+        let moved = apply(&service, &user_id, leaf, move_below(root))
+            .await
+            .unwrap();
+        assert_eq!(moved.task.parent_id, Some(root));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn dependency_cycles_are_rejected() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let mut task_ids = Vec::new();
+        for _ in 0..4 {
+            task_ids.push(create(&service, &user_id, None).await);
+        }
+        let [first, second, third, fourth] = task_ids[..] else {
+            unreachable!()
+        };
+
+        assert!(is_validation(block(&service, &user_id, first, first).await));
+
+        block(&service, &user_id, first, second).await.unwrap();
+        assert!(is_validation(
+            block(&service, &user_id, second, first).await
+        ));
+
+        // first waits on second, second on third and third on fourth
+        block(&service, &user_id, second, third).await.unwrap();
+        block(&service, &user_id, third, fourth).await.unwrap();
+        assert!(is_validation(
+            block(&service, &user_id, fourth, first).await
+        ));
+        assert!(is_validation(block(&service, &user_id, third, first).await));
+        // a second path to the same blocker is no cycle
+        block(&service, &user_id, first, fourth).await.unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn completing_a_blocked_task_lists_its_open_blockers() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let blocked = create(&service, &user_id, None).await;
+        let first = create(&service, &user_id, None).await;
+        let second = create(&service, &user_id, None).await;
+        block(&service, &user_id, blocked, first).await.unwrap();
+        block(&service, &user_id, blocked, second).await.unwrap();
+
+        let Err(AppError::Conflict(message)) = apply(&service, &user_id, blocked, complete()).await
+        else {
+            panic!("completing a blocked task must conflict");
+        };
+        assert!(message.contains(&first.to_string()), "{}", message);
+        assert!(message.contains(&second.to_string()), "{}", message);
+
+        // only the blockers still open are listed
+        apply(&service, &user_id, first, complete()).await.unwrap();
+        let Err(AppError::Conflict(message)) = apply(&service, &user_id, blocked, complete()).await
+        else {
+            panic!("completing a blocked task must conflict");
+        };
+        assert!(!message.contains(&first.to_string()), "{}", message);
+        assert!(message.contains(&second.to_string()), "{}", message);
+
+        apply(&service, &user_id, second, complete()).await.unwrap();
+        let completed_task = apply(&service, &user_id, blocked, complete())
+            .await
+            .unwrap();
+        assert!(completed_task.task.completed);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn tag_changes_move_the_tagged_tasks_to_a_new_version() {
+        let (service, _) = connect().await;
+        let tags = TagService::new(TagRepository::new(database(), cache().await));
+        let user_id = Uuid::new_v4().to_string();
+        let tagged = service
+            .insert(
+                TaskRequest {
+                    tags: Some(vec!["errands".to_string()]),
+                    ..request("tagged")
+                },
+                user_id.clone(),
+                None,
+                "test".to_string(),
+            )
+            .await
+            .unwrap();
+        let task_id = tagged.task.id.to_string();
+        // read once so the tagged task is cached under its first version
+        service
+            .find_by_id(task_id.clone(), user_id.clone())
+            .await
+            .unwrap();
+        let tag_id = tags.find_all(user_id.clone()).await.unwrap()[0].id;
+
+        tags.update(
+            TagRequest {
+                name: "chores".to_string(),
+            },
+            tag_id.to_string(),
+            user_id.clone(),
+        )
+        .await
+        .unwrap();
+        let renamed = service
+            .find_by_id(task_id.clone(), user_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(renamed.tags, ["chores"]);
+        assert!(renamed.task.version > tagged.task.version);
+
+        // a write made against the ETag read before the rename is refused
+        let stale = service
+            .patch(
+                patch(r#"{"title":"stale"}"#),
+                task_id.clone(),
+                user_id.clone(),
+                Some(vec![tagged.task.version]),
+                "test".to_string(),
+            )
+            .await;
+        assert!(matches!(stale, Err(AppError::PreconditionFailed(_))));
+
+        tags.delete(tag_id.to_string(), user_id.clone())
+            .await
+            .unwrap();
+        let untagged = service.find_by_id(task_id, user_id).await.unwrap();
+        assert!(untagged.tags.is_empty());
+        assert!(untagged.task.version > renamed.task.version);
+    }
+
+    async fn create_with_key(
+        service: &TaskService,
+        user_id: &str,
+        key: &str,
+        task_title: &str,
+    ) -> Result<TaskDetail, AppError> {
+        service
+            .insert(
+                request(task_title),
+                user_id.to_string(),
+                Some(key.to_string()),
+                "test".to_string(),
+            )
+            .await
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn a_replayed_key_returns_the_first_response() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let key = Uuid::new_v4().to_string();
+
+        let first = create_with_key(&service, &user_id, &key, "once")
+            .await
+            .unwrap();
+        let replayed = create_with_key(&service, &user_id, &key, "once")
+            .await
+            .unwrap();
+        assert_eq!(replayed.task.id, first.task.id);
+        assert_eq!(replayed.task.version, first.task.version);
+
+        // the key belongs to the user who sent it
+        let other_user = Uuid::new_v4().to_string();
+        let other = create_with_key(&service, &other_user, &key, "once")
+            .await
+            .unwrap();
+        assert_ne!(other.task.id, first.task.id);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn a_key_reused_for_another_body_is_refused() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let key = Uuid::new_v4().to_string();
+
+        create_with_key(&service, &user_id, &key, "once")
+            .await
+            .unwrap();
+        assert!(matches!(
+            create_with_key(&service, &user_id, &key, "twice").await,
+            Err(AppError::UnprocessableEntity(_))
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the postgres and redis of docker-compose"]
+    async fn concurrent_requests_with_a_key_create_one_task() {
+        let (service, _) = connect().await;
+        let user_id = Uuid::new_v4().to_string();
+        let key = Uuid::new_v4().to_string();
+
+        let (first, second) = future::join(
+            create_with_key(&service, &user_id, &key, "raced"),
+            create_with_key(&service, &user_id, &key, "raced"),
+        )
+        .await;
+        assert_eq!(first.unwrap().task.id, second.unwrap().task.id);
+    }
 }
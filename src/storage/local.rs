@@ -0,0 +1,114 @@
+use crate::configuration::model;
+use crate::model::error::AppError;
+use crate::storage::{BlobDigest, BlobStorage, BlobStream, StoredBlob};
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// blobs are files below the root directory, keys are relative paths
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(config: &model::LocalStorage) -> Result<Self, String> {
+        if config.path.is_empty() {
+            return Err("attachment.local.path cannot be empty".to_string());
+        }
+
+        let root = PathBuf::from(&config.path);
+        std::fs::create_dir_all(&root)
+            .map_err(|err| format!("create {} error: {}", config.path, err))?;
+
+        Ok(LocalStorage { root })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+#[async_trait(?Send)]
+impl BlobStorage for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        body: BlobStream,
+        max_size: u64,
+    ) -> Result<StoredBlob, AppError> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // written next to the blob and renamed over it once complete
+        let partial = path.with_extension("part");
+        let result = write(&partial, body, max_size).await;
+        match result {
+            Ok(blob) => {
+                fs::rename(&partial, &path).await?;
+                Ok(blob)
+            }
+            Err(err) => {
+                let _ = fs::remove_file(&partial).await;
+                Err(err)
+            }
+        }
+    }
+
+    async fn get(&self, key: &str, start: u64, end: u64) -> Result<BlobStream, AppError> {
+        let mut file = File::open(self.path(key)).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+
+        Ok(read(file.take(end - start + 1)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match fs::remove_file(self.path(key)).await {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+pub(super) async fn write(
+    path: &Path,
+    mut body: BlobStream,
+    max_size: u64,
+) -> Result<StoredBlob, AppError> {
+    let mut file = File::create(path).await?;
+    let mut digest = BlobDigest::new(max_size);
+
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        digest.update(&chunk)?;
+        file.write_all(&chunk).await?;
+    }
+    file.sync_all().await?;
+
+    Ok(digest.finish())
+}
+
+// reads the file a chunk at a time, the stream ends after the first error
+pub(super) fn read<R: AsyncRead + Unpin + 'static>(reader: R) -> BlobStream {
+    stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        match reader.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(length) => {
+                buffer.truncate(length);
+                Some((Ok(Bytes::from(buffer)), Some(reader)))
+            }
+            Err(err) => Some((Err(err.into()), None)),
+        }
+    })
+    .boxed_local()
+}
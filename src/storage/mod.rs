@@ -0,0 +1,90 @@
+pub mod local;
+pub mod s3;
+
+use crate::configuration::model::{Attachment, StorageBackend};
+use crate::model::error::AppError;
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use futures_util::stream::LocalBoxStream;
+use ring::digest;
+
+// blobs are streamed on the worker thread that serves the request, the s3
+// client is not Send so neither are the streams
+pub type BlobStream = LocalBoxStream<'static, Result<Bytes, AppError>>;
+
+pub struct StoredBlob {
+    pub size: u64,
+    // hex sha256 of the bytes that were stored
+    pub checksum: String,
+}
+
+#[async_trait(?Send)]
+pub trait BlobStorage: Send + Sync {
+    // a blob is only visible under its key once all of it has been written,
+    // one that grows past max_size is not stored at all
+    async fn put(&self, key: &str, body: BlobStream, max_size: u64)
+        -> Result<StoredBlob, AppError>;
+    // the bytes from start to end, both inclusive
+    async fn get(&self, key: &str, start: u64, end: u64) -> Result<BlobStream, AppError>;
+    // deleting a blob that is not there is not an error
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+pub fn from_config(config: &Attachment) -> Result<Box<dyn BlobStorage>, String> {
+    match config.storage {
+        StorageBackend::Local => {
+            let local = config
+                .local
+                .as_ref()
+                .ok_or("attachment.local is required for local storage")?;
+            Ok(Box::new(local::LocalStorage::new(local)?))
+        }
+        StorageBackend::S3 => {
+            let s3 = config
+                .s3
+                .as_ref()
+                .ok_or("attachment.s3 is required for s3 storage")?;
+            Ok(Box::new(s3::S3Storage::new(s3)?))
+        }
+    }
+}
+
+// counts and hashes a blob as it is written
+pub struct BlobDigest {
+    context: digest::Context,
+    size: u64,
+    max_size: u64,
+}
+
+impl BlobDigest {
+    pub fn new(max_size: u64) -> Self {
+        BlobDigest {
+            context: digest::Context::new(&digest::SHA256),
+            size: 0,
+            max_size,
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), AppError> {
+        self.size += chunk.len() as u64;
+        if self.size > self.max_size {
+            return Err(AppError::PayloadTooLarge(format!(
+                "file cannot be larger than {} bytes",
+                self.max_size
+            )));
+        }
+        self.context.update(chunk);
+        Ok(())
+    }
+
+    pub fn finish(self) -> StoredBlob {
+        StoredBlob {
+            size: self.size,
+            checksum: hex(self.context.finish().as_ref()),
+        }
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
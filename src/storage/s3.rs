@@ -0,0 +1,359 @@
+use crate::configuration::model;
+use crate::model::error::AppError;
+use crate::storage::{hex, local, BlobStorage, BlobStream, StoredBlob};
+use actix_web::body::SizedStream;
+use actix_web::http::{header, Method, StatusCode, Uri};
+use async_trait::async_trait;
+use awc::{Client, ClientRequest};
+use chrono::Utc;
+use futures_util::StreamExt;
+use ring::{digest, hmac};
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs::{self, File};
+use uuid::Uuid;
+
+// how long a request may take until its response starts, bodies stream for as long as they take
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// path style requests signed with signature version 4, so it works against
+// aws as well as minio and the other s3 compatible stores
+pub struct S3Storage {
+    endpoint: String,
+    host: String,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Storage {
+    pub fn new(config: &model::S3Storage) -> Result<Self, String> {
+        let endpoint = config.endpoint.trim_end_matches('/');
+        let uri: Uri = endpoint
+            .parse()
+            .map_err(|err| format!("attachment.s3.endpoint {} is invalid: {}", endpoint, err))?;
+        let host = match (uri.scheme_str(), uri.authority(), uri.path()) {
+            (Some("http" | "https"), Some(authority), "" | "/") => authority.to_string(),
+            _ => {
+                return Err(format!(
+                    "attachment.s3.endpoint {} must be a http or https url without a path",
+                    endpoint
+                ))
+            }
+        };
+        if config.region.is_empty() || config.bucket.is_empty() {
+            return Err("attachment.s3.region and bucket cannot be empty".to_string());
+        }
+
+        // credentials, the environment takes precedence over config
+        let access_key =
+            credential("S3_ACCESS_KEY", &config.access_key).ok_or("s3 access key is not set")?;
+        let secret_key =
+            credential("S3_SECRET_KEY", &config.secret_key).ok_or("s3 secret key is not set")?;
+
+        Ok(S3Storage {
+            endpoint: endpoint.to_string(),
+            host,
+            region: config.region.clone(),
+            bucket: config.bucket.clone(),
+            access_key,
+            secret_key,
+        })
+    }
+
+    fn request(
+        &self,
+        client: &Client,
+        method: Method,
+        key: &str,
+        payload_hash: &str,
+    ) -> ClientRequest {
+        let now = Utc::now();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let path = format!("/{}/{}", encode(&self.bucket), encode(key));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, self.host, payload_hash, timestamp, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            sha256(canonical_request.as_bytes())
+        );
+
+        let mut signing_key = format!("AWS4{}", self.secret_key).into_bytes();
+        for part in [date.as_str(), self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = sign(&signing_key, part.as_bytes());
+        }
+        let signature = hex(&sign(&signing_key, string_to_sign.as_bytes()));
+
+        client
+            .request(method, format!("{}{}", self.endpoint, path))
+            .insert_header(("host", self.host.as_str()))
+            .insert_header(("x-amz-content-sha256", payload_hash))
+            .insert_header(("x-amz-date", timestamp))
+            .insert_header((
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            ))
+    }
+
+    async fn upload(
+        &self,
+        key: &str,
+        spool: &Path,
+        body: BlobStream,
+        max_size: u64,
+    ) -> Result<StoredBlob, AppError> {
+        let blob = local::write(spool, body, max_size).await?;
+        let file = File::open(spool).await?;
+
+        let client = client();
+        let response = self
+            .request(&client, Method::PUT, key, &blob.checksum)
+            .send_body(SizedStream::new(blob.size, local::read(file)))
+            .await
+            .map_err(|err| AppError::Storage(format!("s3 put {} error: {}", key, err)))?;
+        check_status(key, response.status(), &[StatusCode::OK])?;
+
+        Ok(blob)
+    }
+}
+
+#[async_trait(?Send)]
+impl BlobStorage for S3Storage {
+    async fn put(
+        &self,
+        key: &str,
+        body: BlobStream,
+        max_size: u64,
+    ) -> Result<StoredBlob, AppError> {
+        // s3 needs the length before the body, so the upload is spooled to a temporary
+        // file first, then streamed from it with the hash of the file as signed payload
+        let spool = env::temp_dir().join(format!("task-manager-{}.part", Uuid::new_v4()));
+        let result = self.upload(key, &spool, body, max_size).await;
+        let _ = fs::remove_file(&spool).await;
+
+        result
+    }
+
+    async fn get(&self, key: &str, start: u64, end: u64) -> Result<BlobStream, AppError> {
+        let client = client();
+        let response = self
+            .request(&client, Method::GET, key, &sha256(b""))
+            .insert_header(("range", format!("bytes={}-{}", start, end)))
+            .send()
+            .await
+            .map_err(|err| AppError::Storage(format!("s3 get {} error: {}", key, err)))?;
+        let content_length = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        check_range(key, response.status(), content_length, start, end)?;
+
+        Ok(response
+            .map(|chunk| chunk.map_err(|err| AppError::Storage(err.to_string())))
+            .boxed_local())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let client = client();
+        let response = self
+            .request(&client, Method::DELETE, key, &sha256(b""))
+            .send()
+            .await
+            .map_err(|err| AppError::Storage(format!("s3 delete {} error: {}", key, err)))?;
+
+        // s3 answers a delete of a missing object with 204, some stand-ins with 404
+        check_status(
+            key,
+            response.status(),
+            &[
+                StatusCode::OK,
+                StatusCode::NO_CONTENT,
+                StatusCode::NOT_FOUND,
+            ],
+        )
+    }
+}
+
+fn client() -> Client {
+    Client::builder().timeout(TIMEOUT).finish()
+}
+
+fn check_status(key: &str, status: StatusCode, expected: &[StatusCode]) -> Result<(), AppError> {
+    if expected.contains(&status) {
+        return Ok(());
+    }
+
+    Err(AppError::Storage(format!(
+        "s3 request for {} returned {}",
+        key, status
+    )))
+}
+
+// a store that ignores the range answers with the whole object, which is only
+// what was asked for when the range covers all of it
+fn check_range(
+    key: &str,
+    status: StatusCode,
+    content_length: Option<u64>,
+    start: u64,
+    end: u64,
+) -> Result<(), AppError> {
+    match status {
+        StatusCode::PARTIAL_CONTENT => Ok(()),
+        StatusCode::OK if start == 0 && content_length == Some(end + 1) => Ok(()),
+        status => Err(AppError::Storage(format!(
+            "s3 get of bytes {}-{} of {} returned {}",
+            start, end, key, status
+        ))),
+    }
+}
+
+fn credential(variable: &str, configured: &Option<String>) -> Option<String> {
+    env::var(variable)
+        .ok()
+        .or_else(|| configured.clone())
+        .filter(|value| !value.is_empty())
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, bytes).as_ref())
+}
+
+fn sign(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, message).as_ref().to_vec()
+}
+
+// uri encoding as signature version 4 expects it, the slashes of a key are kept
+fn encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::web::Bytes;
+    use futures_util::stream;
+
+    fn storage(endpoint: &str) -> Result<S3Storage, String> {
+        S3Storage::new(&model::S3Storage {
+            endpoint: endpoint.to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "task-manager-attachments".to_string(),
+            access_key: Some("minioadmin".to_string()),
+            secret_key: Some("minioadmin".to_string()),
+        })
+    }
+
+    // the minio of docker-compose, TEST_S3_ENDPOINT points it elsewhere
+    fn minio() -> S3Storage {
+        let endpoint =
+            env::var("TEST_S3_ENDPOINT").unwrap_or_else(|_| "http://localhost:9100".to_string());
+        storage(&endpoint).unwrap()
+    }
+
+    fn body(bytes: &[u8], chunk_size: usize) -> BlobStream {
+        let chunks: Vec<Result<Bytes, AppError>> = bytes
+            .chunks(chunk_size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        stream::iter(chunks).boxed_local()
+    }
+
+    async fn read(mut blob: BlobStream) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = blob.next().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    #[test]
+    fn endpoint_must_not_have_a_path() {
+        assert!(storage("http://localhost:9100").is_ok());
+        assert!(storage("http://localhost:9100/").is_ok());
+        assert!(storage("http://localhost:9100/bucket").is_err());
+        assert!(storage("ftp://localhost:9100").is_err());
+    }
+
+    #[test]
+    fn encode_keeps_slashes() {
+        assert_eq!(encode("task/1/a b+c.png"), "task/1/a%20b%2Bc.png");
+        assert_eq!(encode("ä~"), "%C3%A4~");
+    }
+
+    #[test]
+    fn get_accepts_the_whole_object_only_for_a_full_range() {
+        let accepted = |status, content_length, start, end| {
+            check_range("blob", status, content_length, start, end).is_ok()
+        };
+        assert!(accepted(StatusCode::PARTIAL_CONTENT, Some(5), 10, 14));
+        assert!(accepted(StatusCode::OK, Some(100), 0, 99));
+        // the whole object when only part of it was asked for
+        assert!(!accepted(StatusCode::OK, Some(100), 10, 99));
+        assert!(!accepted(StatusCode::OK, Some(100), 0, 49));
+        assert!(!accepted(StatusCode::OK, None, 0, 99));
+        assert!(!accepted(StatusCode::NOT_FOUND, Some(0), 0, 99));
+        assert!(!accepted(StatusCode::RANGE_NOT_SATISFIABLE, None, 0, 99));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the minio of docker-compose"]
+    async fn put_ranged_get_and_delete() {
+        let storage = minio();
+        let key = format!("test/{}", Uuid::new_v4());
+        let content: Vec<u8> = (0..300_000).map(|index| (index % 251) as u8).collect();
+
+        let blob = storage
+            .put(&key, body(&content, 64 * 1024), 1_000_000)
+            .await
+            .unwrap();
+        assert_eq!(blob.size, content.len() as u64);
+        assert_eq!(blob.checksum, sha256(&content));
+
+        let range = read(storage.get(&key, 1000, 70_999).await.unwrap()).await;
+        assert_eq!(range, &content[1000..71_000]);
+        let last = read(storage.get(&key, 299_999, 299_999).await.unwrap()).await;
+        assert_eq!(last, &content[299_999..]);
+        let all = read(storage.get(&key, 0, 299_999).await.unwrap()).await;
+        assert_eq!(all, content);
+
+        storage.delete(&key).await.unwrap();
+        assert!(storage.get(&key, 0, 0).await.is_err());
+        // deleting it again is not an error
+        storage.delete(&key).await.unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs the minio of docker-compose"]
+    async fn oversized_put_stores_nothing() {
+        let storage = minio();
+        let key = format!("test/{}", Uuid::new_v4());
+        let content = vec![7; 5000];
+
+        let result = storage.put(&key, body(&content, 1000), 4999).await;
+        assert!(matches!(result, Err(AppError::PayloadTooLarge(_))));
+        assert!(storage.get(&key, 0, 0).await.is_err());
+    }
+}
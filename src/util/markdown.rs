@@ -0,0 +1,173 @@
+// the users mentioned as @user in a markdown body, in order of first mention.
+// Code blocks and code spans are skipped, and so is an @ right after a word
+// or a backslash, so e-mail addresses and escaped @ are not mentions
+pub fn mentions(body: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                fence = Some(marker);
+                continue;
+            }
+            (Some(open), Some(marker)) if open == marker => {
+                fence = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, None) => {}
+        }
+
+        for mention in line_mentions(line) {
+            if !mentions.contains(&mention) {
+                mentions.push(mention);
+            }
+        }
+    }
+
+    mentions
+}
+
+fn line_mentions(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut mentions = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            // a code span runs to the next run of as many backticks, an
+            // unmatched run is literal text
+            '`' => {
+                let run = count_run(&chars, i, '`');
+                i += run;
+                if let Some(end) = find_run(&chars, i, run) {
+                    i = end + run;
+                }
+            }
+            '@' if i == 0 || !continues_word(chars[i - 1]) => {
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && is_user_char(chars[end]) {
+                    end += 1;
+                }
+                // punctuation ending a sentence is not part of the name
+                while end > start && matches!(chars[end - 1], '.' | '-') {
+                    end -= 1;
+                }
+                if end > start {
+                    mentions.push(chars[start..end].iter().collect());
+                }
+                i = end.max(start);
+            }
+            _ => i += 1,
+        }
+    }
+
+    mentions
+}
+
+fn count_run(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..]
+        .iter()
+        .take_while(|&&other| other == c)
+        .count()
+}
+
+// the start of the next run of exactly the given number of backticks
+fn find_run(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let length = count_run(chars, i, '`');
+            if length == run {
+                return Some(i);
+            }
+            i += length;
+        } else {
+            i += 1;
+        }
+    }
+
+    None
+}
+
+fn continues_word(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '@' | '\\' | '/')
+}
+
+fn is_user_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_in_order_of_first_mention() {
+        assert_eq!(
+            mentions("@bob and @alice, then @bob again\n@carol"),
+            vec!["bob", "alice", "carol"]
+        );
+        assert!(mentions("no one here").is_empty());
+    }
+
+    #[test]
+    fn trailing_punctuation_is_not_part_of_the_name() {
+        assert_eq!(
+            mentions("thanks @bob. ask @jane.doe- (@alice) @x_y!"),
+            vec!["bob", "jane.doe", "alice", "x_y"]
+        );
+        assert!(mentions("@ @. @-").is_empty());
+    }
+
+    #[test]
+    fn fenced_code_blocks_are_skipped() {
+        let body = "@bob\n```rust\nlet x = @alice;\n```\n@carol\n  ~~~\n@dave\n  ~~~\n@erin";
+        assert_eq!(mentions(body), vec!["bob", "carol", "erin"]);
+    }
+
+    #[test]
+    fn fences_are_closed_by_their_own_marker() {
+        let body = "```\n~~~\n@bob\n```\n@alice";
+        assert_eq!(mentions(body), vec!["alice"]);
+    }
+
+    #[test]
+    fn unterminated_fence_skips_the_rest() {
+        assert_eq!(mentions("@bob\n~~~\n@alice\n\n@carol"), vec!["bob"]);
+    }
+
+    #[test]
+    fn code_spans_are_skipped() {
+        assert_eq!(
+            mentions("`@bob` @alice ``a ` @carol`` @dave"),
+            vec!["alice", "dave"]
+        );
+    }
+
+    #[test]
+    fn unterminated_code_span_is_literal() {
+        assert_eq!(mentions("`@bob and @alice"), vec!["bob", "alice"]);
+        // a run of two is not closed by a single backtick
+        assert_eq!(mentions("``@bob` @alice"), vec!["bob", "alice"]);
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        assert_eq!(
+            mentions("mail bob@example.com or a.b@c.io, not @@alice, but @carol"),
+            vec!["carol"]
+        );
+    }
+
+    #[test]
+    fn escaped_and_path_at_signs_are_not_mentions() {
+        assert_eq!(mentions(r"\@bob docs/@alice @carol"), vec!["carol"]);
+    }
+}
@@ -1 +1,3 @@
+pub mod markdown;
+pub mod rrule;
 pub mod token;
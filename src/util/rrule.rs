@@ -0,0 +1,482 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+// periods walked without producing an occurrence before the rule is
+// considered exhausted, e.g. a monthly rule on the 31st only skips a few
+const MAX_EMPTY_PERIODS: u32 = 1000;
+
+// a larger INTERVAL would put the second occurrence millennia away
+const MAX_INTERVAL: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// a BYDAY entry, the ordinal picks the nth weekday of a month and counts
+// from the end when negative
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByDay {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+// the subset of an iCalendar RRULE (RFC 5545) tasks can repeat on:
+// FREQ, INTERVAL, BYDAY, COUNT and UNTIL, evaluated in UTC with weeks
+// starting on Monday
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub count: Option<u32>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl RRule {
+    // occurrences of the rule from dtstart on, dtstart being the first one
+    // when it matches the rule
+    pub fn occurrences(&self, dtstart: DateTime<Utc>) -> Occurrences {
+        Occurrences {
+            rule: self.clone(),
+            dtstart,
+            period: 0,
+            pending: VecDeque::new(),
+            emitted: 0,
+            done: false,
+        }
+    }
+
+    // the first occurrence strictly after the given time
+    pub fn next_after(
+        &self,
+        dtstart: DateTime<Utc>,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        self.occurrences(dtstart)
+            .find(|occurrence| *occurrence > after)
+    }
+
+    // the occurrences within one period of the rule, None once the period
+    // lies beyond the dates chrono can represent and the series has to end
+    fn candidates(&self, dtstart: DateTime<Utc>, period: i64) -> Option<Vec<DateTime<Utc>>> {
+        let start = dtstart.date_naive();
+        let time = dtstart.time();
+        let step = period.checked_mul(self.interval as i64)?;
+
+        let mut dates: Vec<NaiveDate> = match self.frequency {
+            Frequency::Daily => {
+                let date = add_days(start, step)?;
+                if self.by_day.is_empty()
+                    || self.by_day.iter().any(|day| day.weekday == date.weekday())
+                {
+                    vec![date]
+                } else {
+                    vec![]
+                }
+            }
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday() as u64))?;
+                let monday = add_days(monday, step.checked_mul(7)?)?;
+                if self.by_day.is_empty() {
+                    vec![add_days(
+                        monday,
+                        start.weekday().num_days_from_monday() as i64,
+                    )?]
+                } else {
+                    self.by_day
+                        .iter()
+                        .map(|day| add_days(monday, day.weekday.num_days_from_monday() as i64))
+                        .collect::<Option<Vec<NaiveDate>>>()?
+                }
+            }
+            Frequency::Monthly => {
+                let months =
+                    (start.year() as i64 * 12 + start.month0() as i64).checked_add(step)?;
+                let year = checked_year(months.div_euclid(12))?;
+                let month = months.rem_euclid(12) as u32 + 1;
+                if self.by_day.is_empty() {
+                    // months without the day are skipped, as RFC 5545 does
+                    NaiveDate::from_ymd_opt(year, month, start.day())
+                        .into_iter()
+                        .collect()
+                } else {
+                    self.by_day
+                        .iter()
+                        .flat_map(|day| weekdays_in_month(year, month, *day))
+                        .collect()
+                }
+            }
+            Frequency::Yearly => {
+                let year = checked_year((start.year() as i64).checked_add(step)?)?;
+                // years without the day, a 29th of February, are skipped
+                NaiveDate::from_ymd_opt(year, start.month(), start.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+
+        dates.sort();
+        dates.dedup();
+        Some(
+            dates
+                .into_iter()
+                .map(|date| Utc.from_utc_datetime(&date.and_time(time)))
+                .filter(|occurrence| *occurrence >= dtstart)
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for RRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let value = value
+            .strip_prefix("RRULE:")
+            .or_else(|| value.strip_prefix("rrule:"))
+            .unwrap_or(value);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut count = None;
+        let mut until = None;
+
+        for part in value.split(';').filter(|part| !part.is_empty()) {
+            let (key, part_value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("rrule part {} must be KEY=VALUE", part))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match part_value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => {
+                            return Err(format!(
+                                "FREQ must be one of DAILY, WEEKLY, MONTHLY, YEARLY but got {}",
+                                part_value
+                            ))
+                        }
+                    })
+                }
+                "INTERVAL" => {
+                    interval = match part_value.parse::<u32>() {
+                        Ok(interval) if interval > 0 && interval <= MAX_INTERVAL => interval,
+                        _ => {
+                            return Err(format!(
+                                "INTERVAL must be a number from 1 to {} but got {}",
+                                MAX_INTERVAL, part_value
+                            ))
+                        }
+                    }
+                }
+                "BYDAY" => {
+                    by_day = part_value
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Result<Vec<ByDay>, String>>()?
+                }
+                "COUNT" => {
+                    count = match part_value.parse::<u32>() {
+                        Ok(count) if count > 0 => Some(count),
+                        _ => {
+                            return Err(format!(
+                                "COUNT must be a positive number but got {}",
+                                part_value
+                            ))
+                        }
+                    }
+                }
+                "UNTIL" => until = Some(parse_until(part_value)?),
+                _ => return Err(format!("rrule part {} is not supported", key)),
+            }
+        }
+
+        let frequency = frequency.ok_or_else(|| "rrule must have a FREQ".to_string())?;
+
+        if count.is_some() && until.is_some() {
+            return Err("rrule cannot have both COUNT and UNTIL".to_string());
+        }
+
+        if frequency == Frequency::Yearly && !by_day.is_empty() {
+            return Err("BYDAY is not supported with FREQ=YEARLY".to_string());
+        }
+
+        if frequency != Frequency::Monthly && by_day.iter().any(|day| day.ordinal.is_some()) {
+            return Err("BYDAY ordinals are only supported with FREQ=MONTHLY".to_string());
+        }
+
+        Ok(RRule {
+            frequency,
+            interval,
+            by_day,
+            count,
+            until,
+        })
+    }
+}
+
+pub struct Occurrences {
+    rule: RRule,
+    dtstart: DateTime<Utc>,
+    period: i64,
+    pending: VecDeque<DateTime<Utc>>,
+    emitted: u32,
+    done: bool,
+}
+
+impl Iterator for Occurrences {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut empty_periods = 0;
+        while !self.done && self.pending.is_empty() {
+            let Some(candidates) = self.rule.candidates(self.dtstart, self.period) else {
+                self.done = true;
+                break;
+            };
+            self.period += 1;
+
+            if candidates.is_empty() {
+                empty_periods += 1;
+                self.done = empty_periods >= MAX_EMPTY_PERIODS;
+            }
+            self.pending.extend(candidates);
+        }
+
+        let occurrence = self.pending.pop_front()?;
+
+        if self.rule.count.is_some_and(|count| self.emitted >= count)
+            || self.rule.until.is_some_and(|until| occurrence > until)
+        {
+            self.done = true;
+            self.pending.clear();
+            return None;
+        }
+
+        self.emitted += 1;
+        Some(occurrence)
+    }
+}
+
+fn parse_by_day(value: &str) -> Result<ByDay, String> {
+    let value = value.trim().to_ascii_uppercase();
+    if value.len() < 2 || !value.is_char_boundary(value.len() - 2) {
+        return Err(format!("BYDAY {} is not a valid weekday", value));
+    }
+
+    let (ordinal, weekday) = value.split_at(value.len() - 2);
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return Err(format!("BYDAY {} is not a valid weekday", value)),
+    };
+
+    let ordinal = match ordinal {
+        "" => None,
+        ordinal => match ordinal.parse::<i32>() {
+            Ok(ordinal) if ordinal != 0 && (-5..=5).contains(&ordinal) => Some(ordinal),
+            _ => return Err(format!("BYDAY {} has an invalid ordinal", value)),
+        },
+    };
+
+    Ok(ByDay { ordinal, weekday })
+}
+
+// UNTIL is a UTC date-time, or a date that includes the whole day
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(until) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default();
+        return Ok(Utc.from_utc_datetime(&until.and_time(end_of_day)));
+    }
+
+    chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .map(|until| Utc.from_utc_datetime(&until))
+        .map_err(|_| {
+            format!(
+                "UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ but got {}",
+                value
+            )
+        })
+}
+
+fn weekdays_in_month(year: i32, month: u32, by_day: ByDay) -> Vec<NaiveDate> {
+    let days: Vec<NaiveDate> = (1..=31)
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .filter(|date| date.weekday() == by_day.weekday)
+        .collect();
+
+    match by_day.ordinal {
+        None => days,
+        Some(ordinal) if ordinal > 0 => days
+            .get(ordinal as usize - 1)
+            .copied()
+            .into_iter()
+            .collect(),
+        Some(ordinal) => days
+            .len()
+            .checked_sub(ordinal.unsigned_abs() as usize)
+            .and_then(|index| days.get(index).copied())
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_days(Days::new(u64::try_from(days).ok()?))
+}
+
+// a year within the range of NaiveDate, so a date that does not exist in it
+// is told apart from a series that has run past the last representable year
+fn checked_year(year: i64) -> Option<i32> {
+    let year = i32::try_from(year).ok()?;
+    (NaiveDate::MIN.year()..=NaiveDate::MAX.year())
+        .contains(&year)
+        .then_some(year)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 9, 0, 0).unwrap()
+    }
+
+    fn dates(rule: &str, dtstart: DateTime<Utc>, limit: usize) -> Vec<DateTime<Utc>> {
+        RRule::from_str(rule)
+            .unwrap()
+            .occurrences(dtstart)
+            .take(limit)
+            .collect()
+    }
+
+    #[test]
+    fn parse_rejects_invalid_rules() {
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=1001",
+            "FREQ=DAILY;INTERVAL=100000000",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20240101",
+            "FREQ=DAILY;UNTIL=2024-01-01",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=DAILY;BYMONTH=1",
+            "FREQ=DAILY;INTERVAL",
+        ] {
+            assert!(RRule::from_str(rule).is_err(), "{} should not parse", rule);
+        }
+    }
+
+    #[test]
+    fn parse_accepts_prefix_and_lower_case() {
+        let rule = RRule::from_str("RRULE:freq=weekly;interval=2;byday=mo,fr").unwrap();
+        assert_eq!(rule.frequency, Frequency::Weekly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.by_day.len(), 2);
+    }
+
+    #[test]
+    fn monthly_last_friday() {
+        assert_eq!(
+            dates("FREQ=MONTHLY;BYDAY=-1FR", at(2024, 1, 1), 3),
+            vec![at(2024, 1, 26), at(2024, 2, 23), at(2024, 3, 29)]
+        );
+    }
+
+    #[test]
+    fn monthly_fifth_monday_skips_months_without_one() {
+        assert_eq!(
+            dates("FREQ=MONTHLY;BYDAY=5MO", at(2024, 1, 1), 3),
+            vec![at(2024, 1, 29), at(2024, 4, 29), at(2024, 7, 29)]
+        );
+    }
+
+    #[test]
+    fn monthly_on_the_31st_skips_shorter_months() {
+        assert_eq!(
+            dates("FREQ=MONTHLY", at(2024, 1, 31), 4),
+            vec![
+                at(2024, 1, 31),
+                at(2024, 3, 31),
+                at(2024, 5, 31),
+                at(2024, 7, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_by_day_with_interval() {
+        assert_eq!(
+            dates("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE", at(2024, 1, 3), 3),
+            vec![at(2024, 1, 3), at(2024, 1, 15), at(2024, 1, 17)]
+        );
+    }
+
+    #[test]
+    fn count_ends_the_series() {
+        assert_eq!(
+            dates("FREQ=DAILY;COUNT=3", at(2024, 1, 1), 10),
+            vec![at(2024, 1, 1), at(2024, 1, 2), at(2024, 1, 3)]
+        );
+    }
+
+    #[test]
+    fn until_date_includes_the_whole_day() {
+        assert_eq!(
+            dates("FREQ=DAILY;UNTIL=20240105", at(2024, 1, 1), 10).len(),
+            5
+        );
+        assert_eq!(
+            dates("FREQ=DAILY;UNTIL=20240105T080000Z", at(2024, 1, 1), 10).len(),
+            4
+        );
+    }
+
+    #[test]
+    fn series_ends_at_the_last_representable_date() {
+        for rule in [
+            "FREQ=DAILY;INTERVAL=1000",
+            "FREQ=WEEKLY;INTERVAL=1000",
+            "FREQ=MONTHLY;INTERVAL=1000",
+            "FREQ=YEARLY;INTERVAL=1000",
+        ] {
+            let occurrences = dates(rule, at(2024, 1, 1), 1_000_000);
+            assert!(!occurrences.is_empty(), "{}", rule);
+            assert!(occurrences.len() < 1_000_000, "{} should end", rule);
+        }
+    }
+
+    #[test]
+    fn next_after_skips_past_occurrences() {
+        let rule = RRule::from_str("FREQ=WEEKLY").unwrap();
+        assert_eq!(
+            rule.next_after(at(2024, 1, 1), at(2024, 1, 10)),
+            Some(at(2024, 1, 15))
+        );
+        let rule = RRule::from_str("FREQ=DAILY;COUNT=2").unwrap();
+        assert_eq!(rule.next_after(at(2024, 1, 1), at(2024, 1, 2)), None);
+    }
+}
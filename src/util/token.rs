@@ -1,30 +1,340 @@
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use log::error;
+use crate::configuration::model::Auth;
+use crate::model::auth::Principal;
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
-use std::{env, error::Error};
+use std::{env, error::Error, fs, str::FromStr};
 
 #[derive(Debug, Serialize, Deserialize)]
-
 struct ClaimsToken {
-    user_id: String,
+    sub: Option<String>,
+    user_id: Option<String>,
+}
+
+struct VerificationKey {
+    key_id: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
 }
 
-#[allow(unused)]
-pub fn get_user_id(token: &str) -> Result<String, Box<dyn Error>> {
-    let secret = match env::var("JWT_SECRET") {
-        Ok(secret) => secret,
-        Err(err) => {
-            error!("get secret from env error: {:}", err);
-            let default_secret = "Th1$!sS3cr3t".to_string();
-            default_secret
+pub struct TokenVerifier {
+    keys: Vec<VerificationKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway: u64,
+}
+
+impl TokenVerifier {
+    pub fn from_config(config: &Auth) -> Result<Self, Box<dyn Error>> {
+        let algorithm = Algorithm::from_str(&config.algorithm)?;
+        let mut keys = Vec::new();
+
+        // shared secret, the environment takes precedence over config
+        let secret = env::var("JWT_SECRET")
+            .ok()
+            .or_else(|| config.secret.clone());
+        if let Some(secret) = secret.filter(|secret| !secret.is_empty()) {
+            if !matches!(
+                algorithm,
+                Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+            ) {
+                return Err(format!("secret cannot be used with {:?}", algorithm).into());
+            }
+
+            keys.push(VerificationKey {
+                key_id: None,
+                algorithm,
+                key: DecodingKey::from_secret(secret.as_bytes()),
+            });
+        }
+
+        // pem encoded public key
+        if let Some(path) = &config.public_key_path {
+            let pem = fs::read(path)?;
+            let key = match algorithm {
+                Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                    DecodingKey::from_rsa_pem(&pem)?
+                }
+                Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(&pem)?,
+                _ => return Err(format!("public key cannot be used with {:?}", algorithm).into()),
+            };
+
+            keys.push(VerificationKey {
+                key_id: None,
+                algorithm,
+                key,
+            });
+        }
+
+        // local jwks file
+        if let Some(path) = &config.jwks_path {
+            let jwks: JwkSet = serde_json::from_slice(&fs::read(path)?)?;
+            for jwk in jwks.keys.iter() {
+                let key_algorithm = match jwk.common.algorithm {
+                    Some(key_algorithm) => key_algorithm,
+                    None => match &jwk.algorithm {
+                        AlgorithmParameters::RSA(_) => Algorithm::RS256,
+                        AlgorithmParameters::EllipticCurve(params)
+                            if params.curve == EllipticCurve::P384 =>
+                        {
+                            Algorithm::ES384
+                        }
+                        AlgorithmParameters::EllipticCurve(_) => Algorithm::ES256,
+                        AlgorithmParameters::OctetKey(_) => Algorithm::HS256,
+                        AlgorithmParameters::OctetKeyPair(_) => Algorithm::EdDSA,
+                    },
+                };
+
+                keys.push(VerificationKey {
+                    key_id: jwk.common.key_id.clone(),
+                    algorithm: key_algorithm,
+                    key: DecodingKey::from_jwk(jwk)?,
+                });
+            }
         }
-    };
 
-    let token_message = decode::<ClaimsToken>(
-        &token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::new(Algorithm::HS256),
-    )?;
+        if keys.is_empty() {
+            return Err("no jwt key material configured".into());
+        }
+
+        Ok(TokenVerifier {
+            keys,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            leeway: config.leeway,
+        })
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Principal, JwtError> {
+        let header = decode_header(token)?;
+
+        // only keys registered for the token algorithm are candidates,
+        // narrowed down by key id when the token carries one
+        let candidates = self.keys.iter().filter(|key| {
+            key.algorithm == header.alg
+                && match (&header.kid, &key.key_id) {
+                    (Some(kid), Some(key_id)) => kid == key_id,
+                    _ => true,
+                }
+        });
+
+        let mut last_error: JwtError = ErrorKind::InvalidAlgorithm.into();
+        for candidate in candidates {
+            match decode::<ClaimsToken>(token, &candidate.key, &self.validation(header.alg)) {
+                Ok(token_message) => {
+                    let claims = token_message.claims;
+                    let user_id = claims
+                        .sub
+                        .or(claims.user_id)
+                        .filter(|user_id| !user_id.is_empty())
+                        .ok_or_else(|| ErrorKind::MissingRequiredClaim("sub".to_string()))?;
+
+                    return Ok(Principal { user_id });
+                }
+                Err(err) => last_error = err,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    fn validation(&self, algorithm: Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = self.leeway;
+        validation.validate_nbf = true;
+
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+        }
+
+        validation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const SECRET: &str = "test-secret";
+
+    fn testdata(file: &str) -> String {
+        format!("{}/src/util/testdata/{}", env!("CARGO_MANIFEST_DIR"), file)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "sub": "alice",
+            "iss": "https://issuer.test",
+            "aud": "task-manager",
+            "exp": now() + 600,
+        })
+    }
+
+    fn hs_verifier() -> TokenVerifier {
+        TokenVerifier::from_config(&Auth {
+            algorithm: "HS256".to_string(),
+            secret: Some(SECRET.to_string()),
+            issuer: Some("https://issuer.test".to_string()),
+            audience: Some("task-manager".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn jwks_verifier() -> TokenVerifier {
+        TokenVerifier::from_config(&Auth {
+            algorithm: "RS256".to_string(),
+            jwks_path: Some(testdata("jwks.json")),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn hs_token(claims: &Value) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            claims,
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn rs_token(key_file: &str, kid: Option<&str>, claims: &Value) -> String {
+        let pem = fs::read(testdata(key_file)).unwrap();
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = kid.map(str::to_string);
+        encode(&header, claims, &EncodingKey::from_rsa_pem(&pem).unwrap()).unwrap()
+    }
+
+    fn error_kind(result: Result<Principal, JwtError>) -> ErrorKind {
+        result.expect_err("token was accepted").into_kind()
+    }
+
+    #[test]
+    fn valid_token_carries_the_subject() {
+        let principal = hs_verifier().verify(&hs_token(&claims())).unwrap();
+        assert_eq!(principal.user_id, "alice");
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let mut claims = claims();
+        claims["exp"] = json!(now() - 600);
+        assert_eq!(
+            error_kind(hs_verifier().verify(&hs_token(&claims))),
+            ErrorKind::ExpiredSignature
+        );
+    }
+
+    #[test]
+    fn token_not_valid_yet_is_rejected() {
+        let mut claims = claims();
+        claims["nbf"] = json!(now() + 300);
+        assert_eq!(
+            error_kind(hs_verifier().verify(&hs_token(&claims))),
+            ErrorKind::ImmatureSignature
+        );
+    }
+
+    #[test]
+    fn wrong_issuer_or_audience_is_rejected() {
+        let mut claims = claims();
+        claims["iss"] = json!("https://other.test");
+        assert_eq!(
+            error_kind(hs_verifier().verify(&hs_token(&claims))),
+            ErrorKind::InvalidIssuer
+        );
+
+        let mut claims = self::claims();
+        claims["aud"] = json!("other-service");
+        assert_eq!(
+            error_kind(hs_verifier().verify(&hs_token(&claims))),
+            ErrorKind::InvalidAudience
+        );
+    }
+
+    #[test]
+    fn token_without_subject_is_rejected() {
+        let mut claims = claims();
+        claims.as_object_mut().unwrap().remove("sub");
+        assert!(matches!(
+            error_kind(hs_verifier().verify(&hs_token(&claims))),
+            ErrorKind::MissingRequiredClaim(_)
+        ));
+    }
+
+    // a token signed with hmac over the public key must not pass as rsa
+    #[test]
+    fn hs_token_is_rejected_by_rsa_keys() {
+        let public_key = fs::read(testdata("first.pub.pem")).unwrap();
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims(),
+            &EncodingKey::from_secret(&public_key),
+        )
+        .unwrap();
+
+        let pem_verifier = TokenVerifier::from_config(&Auth {
+            algorithm: "RS256".to_string(),
+            public_key_path: Some(testdata("first.pub.pem")),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            error_kind(pem_verifier.verify(&token)),
+            ErrorKind::InvalidAlgorithm
+        );
+        assert_eq!(
+            error_kind(jwks_verifier().verify(&token)),
+            ErrorKind::InvalidAlgorithm
+        );
+    }
+
+    #[test]
+    fn jwks_key_is_selected_by_kid() {
+        let verifier = jwks_verifier();
+
+        for (key_file, kid) in [("first.pem", "first"), ("second.pem", "second")] {
+            let token = rs_token(key_file, Some(kid), &claims());
+            assert_eq!(verifier.verify(&token).unwrap().user_id, "alice");
+        }
+
+        // the kid names the key to check, another key that matches does not count
+        let token = rs_token("second.pem", Some("first"), &claims());
+        assert_eq!(
+            error_kind(verifier.verify(&token)),
+            ErrorKind::InvalidSignature
+        );
+        let token = rs_token("second.pem", Some("unknown"), &claims());
+        assert!(verifier.verify(&token).is_err());
+
+        // without a kid every key of the algorithm is tried
+        let token = rs_token("second.pem", None, &claims());
+        assert_eq!(verifier.verify(&token).unwrap().user_id, "alice");
+    }
 
-    Ok(token_message.claims.user_id)
+    #[test]
+    fn secret_cannot_be_used_with_rsa() {
+        let result = TokenVerifier::from_config(&Auth {
+            algorithm: "RS256".to_string(),
+            secret: Some(SECRET.to_string()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
 }